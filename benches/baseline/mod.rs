@@ -0,0 +1,742 @@
+// the scanner as it was before it scanned the source in place: the whole source is first
+// split into grapheme clusters, and tokens keep their trivia and doc comments inline. It is
+// only kept to be measured against.
+#![allow(dead_code)]
+
+use rlox::span::Span;
+use rlox::token_type::TokenType;
+use rlox::trivia::TriviaKind;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanError<'a> {
+    UnexpectedCharacter(&'a str, Span),
+    UnterminatedString(Span),
+    UnterminatedComment(Span),
+    InvalidEscape(&'a str, Span),
+    InvalidUnicodeEscape(Span),
+    InvalidNumber(&'a str, &'static str, Span),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Trivia<'a> {
+    pub fn new(kind: TriviaKind, text: &'a str, span: Span) -> Self {
+        Self { kind, text, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub typ: TokenType<'a>,
+    pub lexeme: &'a str,
+    pub span: Span,
+    pub doc: Option<Cow<'a, str>>,
+    pub leading: Vec<Trivia<'a>>,
+    pub trailing: Vec<Trivia<'a>>,
+    pub normalized: Option<Box<str>>,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(typ: TokenType<'a>, lexeme: &'a str, span: Span) -> Self {
+        Self {
+            typ,
+            lexeme,
+            span,
+            doc: None,
+            leading: Vec::new(),
+            trailing: Vec::new(),
+            normalized: None,
+        }
+    }
+}
+
+// the text of the grapheme clusters `start..end`, which lie next to each other in `text`
+fn take_slice<'a>(text: &'a str, source: &[&'a str], start: usize, end: usize) -> &'a str {
+    let slices = &source[start..end];
+    let Some(first) = slices.first() else {
+        return "";
+    };
+    let offset = first.as_ptr() as usize - text.as_ptr() as usize;
+    let len = slices.iter().map(|slice| slice.len()).sum::<usize>();
+    &text[offset..offset + len]
+}
+
+pub struct Scanner<'a> {
+    // the source the grapheme clusters were split from
+    text: &'a str,
+    source: Vec<&'a str>,
+    tokens: Vec<Token<'a>>,
+    errors: Vec<ScanError<'a>>,
+    // `start` and `current` index grapheme clusters, the `_byte` fields track the same
+    // positions as byte offsets into the source
+    start: usize,
+    current: usize,
+    start_byte: usize,
+    current_byte: usize,
+    line: usize,
+    // index of the first grapheme cluster on the current line
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
+    // one entry per `${` still open: the unmatched `{` inside it and the span of the opening
+    // quote of the string it belongs to
+    interpolations: Vec<(usize, Span)>,
+    // doc comment waiting to be attached to the next token
+    doc: Option<Cow<'a, str>>,
+    // whether whitespace and comments are kept as trivia on the tokens
+    lossless: bool,
+    // trivia waiting to lead the next token
+    leading: Vec<Trivia<'a>>,
+    // whether a newline separates the cursor from the last token, so new trivia leads the
+    // next token rather than trailing that one
+    line_ended: bool,
+    // byte offset up to which the source is covered by tokens or trivia
+    accounted: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            text: source,
+            source: source.graphemes(true).collect(),
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            start: 0,
+            current: 0,
+            start_byte: 0,
+            current_byte: 0,
+            line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
+            interpolations: Vec::new(),
+            doc: None,
+            lossless: false,
+            leading: Vec::new(),
+            line_ended: true,
+            accounted: 0,
+        }
+    }
+
+    // keeps whitespace, comments and unscannable text as trivia on the tokens, so that their
+    // `source_text` joined together gives back the source byte for byte
+    pub fn with_trivia(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+
+    pub fn scan_tokens(self) -> Result<Vec<Token<'a>>, Vec<ScanError<'a>>> {
+        let (tokens, errors) = self.scan_partial();
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // keeps going past lexical errors, returning every token that did scan alongside every
+    // error that was found
+    pub fn scan_partial(mut self) -> (Vec<Token<'a>>, Vec<ScanError<'a>>) {
+        while self.scan_token().is_some() {}
+
+        // the string around an unclosed `${` never ended either
+        for (_, opening) in std::mem::take(&mut self.interpolations) {
+            self.errors.push(ScanError::UnterminatedString(opening));
+        }
+
+        self.mark_start();
+        self.add_token(TokenType::EOF);
+        (self.tokens, self.errors)
+    }
+
+    fn scan_token(&mut self) -> Option<()> {
+        self.whitespace();
+
+        self.mark_start();
+        let c = self.advance()?;
+        self.token(c);
+
+        // whatever the scanner gave up on is kept verbatim
+        if self.lossless && self.accounted < self.current_byte {
+            self.add_trivia(TriviaKind::Skipped);
+        }
+        Some(())
+    }
+
+    fn whitespace(&mut self) {
+        loop {
+            self.mark_start();
+            match self.peek() {
+                Some(c) if is_newline(c) => {
+                    // will never be None
+                    self.advance();
+                    self.add_trivia(TriviaKind::Newline);
+                }
+                Some(" " | "\t" | "\r") => {
+                    self.advance_while(|c| c == " " || c == "\t" || c == "\r");
+                    self.add_trivia(TriviaKind::Whitespace);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn token(&mut self, c: &'a str) {
+        match c {
+            "(" => self.add_token(TokenType::LEFT_PAREN),
+            ")" => self.add_token(TokenType::RIGHT_PAREN),
+            "{" => {
+                if let Some((depth, _)) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE);
+            }
+            "}" => match self.interpolations.last_mut() {
+                // closes the `${`, so the string picks up where it left off
+                Some((0, opening)) => {
+                    let opening = *opening;
+                    self.interpolations.pop();
+                    self.string(opening);
+                }
+                Some((depth, _)) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RIGHT_BRACE);
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE),
+            },
+            "," => self.add_token(TokenType::COMMA),
+            "." => {
+                // `.5` is a number unless the dot could be a property access
+                let follows_operand = self.tokens.last().is_some_and(|t| {
+                    matches!(
+                        t.typ,
+                        TokenType::IDENTIFIER
+                            | TokenType::RIGHT_PAREN
+                            | TokenType::THIS
+                            | TokenType::NUMBER(_)
+                            | TokenType::STRING(_)
+                    )
+                });
+                if !follows_operand && self.peek().is_some_and(is_digit) {
+                    self.number();
+                } else {
+                    self.add_token(TokenType::DOT);
+                }
+            }
+            "-" => self.add_token(TokenType::MINUS),
+            "+" => self.add_token(TokenType::PLUS),
+            ";" => self.add_token(TokenType::SEMICOLON),
+            "*" => self.add_token(TokenType::STAR),
+            "!" => {
+                let token_type = if self.advance_if_match("=") {
+                    TokenType::BANG_EQUAL
+                } else {
+                    TokenType::BANG
+                };
+                self.add_token(token_type);
+            }
+            "=" => {
+                let token_type = if self.advance_if_match("=") {
+                    TokenType::EQUAL_EQUAL
+                } else {
+                    TokenType::EQUAL
+                };
+                self.add_token(token_type);
+            }
+            "<" => {
+                let token_type = if self.advance_if_match("=") {
+                    TokenType::LESS_EQUAL
+                } else {
+                    TokenType::LESS
+                };
+                self.add_token(token_type);
+            }
+            ">" => {
+                let token_type = if self.advance_if_match("=") {
+                    TokenType::GREATER_EQUAL
+                } else {
+                    TokenType::GREATER
+                };
+                self.add_token(token_type);
+            }
+            "/" => {
+                if self.advance_if_match("/") {
+                    self.slash_slash_comment();
+                } else if self.advance_if_match("*") {
+                    self.slash_star_comment();
+                } else {
+                    self.add_token(TokenType::SLASH);
+                }
+            }
+            "\"" => {
+                let opening = self.opening_span(1);
+                self.string(opening);
+            }
+            _ => {
+                if is_digit(c) {
+                    self.number();
+                } else if is_alpha(c) {
+                    self.identifier();
+                } else {
+                    let span = self.span();
+                    self.errors.push(ScanError::UnexpectedCharacter(c, span));
+                }
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.source.get(self.current).copied()
+    }
+
+    fn peek_next(&self) -> Option<&'a str> {
+        self.source.get(self.current + 1).copied()
+    }
+
+    fn advance(&mut self) -> Option<&'a str> {
+        let &c = self.source.get(self.current)?;
+        self.current += 1;
+        self.current_byte += c.len();
+        if is_newline(c) {
+            self.line += 1;
+            self.line_start = self.current;
+        }
+        Some(c)
+    }
+
+    fn mark_start(&mut self) {
+        self.start = self.current;
+        self.start_byte = self.current_byte;
+        self.start_line = self.line;
+        self.start_column = self.current - self.line_start + 1;
+    }
+
+    // the span from the start of the current token up to the cursor
+    fn span(&self) -> Span {
+        Span::new(
+            self.start_byte,
+            self.current_byte,
+            self.start_line,
+            self.start_column,
+        )
+    }
+
+    fn advance_if_match(&mut self, expected: &str) -> bool {
+        self.peek()
+            .is_some_and(|c| c == expected)
+            .then(|| {
+                // will never be None
+                self.advance()
+            })
+            .is_some()
+    }
+
+    fn advance_while(&mut self, predicate: fn(&str) -> bool) {
+        while self.peek().is_some_and(predicate) {
+            // will never be None
+            self.advance();
+        }
+    }
+
+    // an empty span at the cursor
+    fn cursor_span(&self) -> Span {
+        Span::new(
+            self.current_byte,
+            self.current_byte,
+            self.line,
+            self.current - self.line_start + 1,
+        )
+    }
+
+    // the span of the first `len` bytes of the current token, such as an opening delimiter
+    fn opening_span(&self, len: usize) -> Span {
+        Span::new(
+            self.start_byte,
+            self.start_byte + len,
+            self.start_line,
+            self.start_column,
+        )
+    }
+
+    fn add_token(&mut self, typ: TokenType<'a>) {
+        let mut token = Token::new(
+            typ,
+            take_slice(self.text, &self.source, self.start, self.current),
+            self.span(),
+        );
+        token.doc = self.doc.take();
+        token.leading = std::mem::take(&mut self.leading);
+        self.tokens.push(token);
+        self.line_ended = false;
+        self.accounted = self.current_byte;
+    }
+
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        self.accounted = self.current_byte;
+        if !self.lossless {
+            return;
+        }
+        let text = take_slice(self.text, &self.source, self.start, self.current);
+        let trivia = Trivia::new(kind, text, self.span());
+        match self.tokens.last_mut() {
+            Some(token) if !self.line_ended => token.trailing.push(trivia),
+            _ => self.leading.push(trivia),
+        }
+        if kind == TriviaKind::Newline || text.contains('\n') {
+            self.line_ended = true;
+        }
+    }
+
+    // consecutive doc comments are joined into one
+    fn push_doc(&mut self, text: Cow<'a, str>) {
+        self.doc = Some(match self.doc.take() {
+            Some(doc) => Cow::Owned(format!("{}\n{}", doc, text)),
+            None => text,
+        });
+    }
+
+    // scans one segment of a string, starting after its opening `"` or after the `}` closing
+    // an interpolation; a segment ending in `${` becomes an INTERPOLATION token
+    fn string(&mut self, opening: Span) {
+        // only allocated once the literal turns out to contain an escape sequence
+        let mut unescaped: Option<String> = None;
+        let interpolation = loop {
+            match self.peek() {
+                None => {
+                    self.errors.push(ScanError::UnterminatedString(opening));
+                    return;
+                }
+                Some("\"") => break false,
+                Some("$") if self.peek_next() == Some("{") => break true,
+                Some("\\") => {
+                    let unescaped = unescaped.get_or_insert_with(|| {
+                        take_slice(self.text, &self.source, self.start + 1, self.current)
+                            .to_string()
+                    });
+                    let escape = self.cursor_span();
+                    // will never be None
+                    self.advance();
+                    match self.escape() {
+                        Ok(Some(c)) => unescaped.push(c),
+                        // the string is unterminated, which the next iteration reports
+                        Ok(None) => {}
+                        Err(e) => {
+                            let span = escape.to(self.cursor_span());
+                            self.errors.push(match e {
+                                Some(c) => ScanError::InvalidEscape(c, span),
+                                None => ScanError::InvalidUnicodeEscape(span),
+                            });
+                        }
+                    }
+                }
+                Some(c) => {
+                    if let Some(unescaped) = &mut unescaped {
+                        unescaped.push_str(c);
+                    }
+                    // will never be None
+                    self.advance();
+                }
+            }
+        };
+
+        let end = self.current;
+        // consume the closing " or the "${"
+        // will never be None
+        self.advance();
+        if interpolation {
+            self.advance();
+        }
+
+        let literal = match unescaped {
+            Some(unescaped) => Cow::Owned(unescaped),
+            None => Cow::Borrowed(take_slice(self.text, &self.source, self.start + 1, end)),
+        };
+        if interpolation {
+            self.interpolations.push((0, opening));
+            self.add_token(TokenType::INTERPOLATION(literal));
+        } else {
+            self.add_token(TokenType::STRING(literal));
+        }
+    }
+
+    // decodes the escape sequence following a consumed backslash; an unknown escape is
+    // reported with its character, a malformed \u{...} without one
+    fn escape(&mut self) -> Result<Option<char>, Option<&'a str>> {
+        let Some(c) = self.advance() else {
+            return Ok(None);
+        };
+        let escaped = match c {
+            "n" => '\n',
+            "t" => '\t',
+            "r" => '\r',
+            "\\" => '\\',
+            "\"" => '"',
+            "$" => '$',
+            "0" => '\0',
+            "u" => return self.unicode_escape().map(Some).ok_or(None),
+            _ => return Err(Some(c)),
+        };
+        Ok(Some(escaped))
+    }
+
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.advance_if_match("{") {
+            return None;
+        }
+        let digits_start = self.current;
+        self.advance_while(|c| c.chars().all(|c| c.is_ascii_hexdigit()));
+        let digits = take_slice(self.text, &self.source, digits_start, self.current);
+        if !self.advance_if_match("}") || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    fn slash_slash_comment(&mut self) {
+        // `///` starts a doc comment, `////` and longer are plain comments again
+        let doc = self.peek() == Some("/") && self.peek_next() != Some("/");
+        self.advance_while(|c| !is_newline(c));
+
+        if doc {
+            let text = take_slice(self.text, &self.source, self.start + 3, self.current);
+            let text = text.strip_prefix(' ').unwrap_or(text).trim_end();
+            self.push_doc(Cow::Borrowed(text));
+            self.add_trivia(TriviaKind::DocComment);
+        } else {
+            self.add_trivia(TriviaKind::LineComment);
+        }
+    }
+
+    // block comments nest, so commenting out code that already has one keeps working
+    fn slash_star_comment(&mut self) {
+        // `/**` starts a doc comment, but `/**/` and `/***` do not
+        let doc = self.peek() == Some("*") && !matches!(self.peek_next(), Some("*" | "/"));
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.peek(), self.peek_next()) {
+                (None, _) => {
+                    let span = self.opening_span(2);
+                    self.errors.push(ScanError::UnterminatedComment(span));
+                    return;
+                }
+                (Some("/"), Some("*")) => depth += 1,
+                (Some("*"), Some("/")) => depth -= 1,
+                _ => {
+                    // will never be None
+                    self.advance();
+                    continue;
+                }
+            }
+            // consume the "/*" or "*/"
+            // will never be None
+            self.advance();
+            self.advance();
+        }
+
+        if doc {
+            let text = take_slice(self.text, &self.source, self.start + 3, self.current - 2);
+            self.push_doc(Cow::Owned(block_doc(text)));
+            self.add_trivia(TriviaKind::DocComment);
+        } else {
+            self.add_trivia(TriviaKind::BlockComment);
+        }
+    }
+
+    // the first digit, or the dot of a leading-dot literal, has already been consumed
+    fn number(&mut self) {
+        let first = self.source[self.start];
+        let radix = match (first, self.peek()) {
+            ("0", Some("x" | "X")) => 16,
+            ("0", Some("o" | "O")) => 8,
+            ("0", Some("b" | "B")) => 2,
+            _ => 10,
+        };
+
+        let result = if radix == 10 {
+            self.decimal(first != ".")
+        } else {
+            // consume the prefix
+            // will never be None
+            self.advance();
+            self.prefixed(radix)
+        };
+
+        // a literal running straight into letters or digits is malformed as a whole
+        let result = result.and_then(|value| {
+            if self.peek().is_some_and(is_alphanumeric) {
+                self.advance_while(is_alphanumeric);
+                Err("unexpected characters after the number")
+            } else {
+                Ok(value)
+            }
+        });
+
+        match result {
+            Ok(value) => self.add_token(TokenType::NUMBER(value)),
+            Err(reason) => {
+                self.advance_while(is_alphanumeric);
+                let literal = take_slice(self.text, &self.source, self.start, self.current);
+                let span = self.span();
+                self.errors
+                    .push(ScanError::InvalidNumber(literal, reason, span));
+            }
+        }
+    }
+
+    fn decimal(&mut self, has_integer_part: bool) -> Result<f64, &'static str> {
+        if has_integer_part {
+            self.digits(10, true)?;
+
+            // look for a fractional part
+            let next_is_digit = self.peek_next().is_some_and(is_digit);
+            if self.peek() == Some(".") && next_is_digit {
+                // consume the "."
+                // will never be None
+                self.advance();
+                self.digits(10, false)?;
+            }
+        } else {
+            self.digits(10, false)?;
+        }
+
+        // look for an exponent
+        if self.advance_if_match("e") || self.advance_if_match("E") {
+            if !self.advance_if_match("+") {
+                self.advance_if_match("-");
+            }
+            if !self.digits(10, false)? {
+                return Err("expected digits in the exponent");
+            }
+        }
+
+        let literal =
+            take_slice(self.text, &self.source, self.start, self.current).replace('_', "");
+        literal.parse::<f64>().map_err(|_| "not a valid number")
+    }
+
+    fn prefixed(&mut self, radix: u32) -> Result<f64, &'static str> {
+        let digits_start = self.current;
+        if !self.digits(radix, false)? {
+            return Err(match radix {
+                16 => "expected hexadecimal digits after '0x'",
+                8 => "expected octal digits after '0o'",
+                _ => "expected binary digits after '0b'",
+            });
+        }
+
+        let digits = take_slice(self.text, &self.source, digits_start, self.current);
+        Ok(digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64))
+    }
+
+    // consumes a run of digits in `radix`, where `_` may only sit between two digits, and
+    // returns whether there were any digits (counting one already consumed as `seen`)
+    fn digits(&mut self, radix: u32, mut seen: bool) -> Result<bool, &'static str> {
+        loop {
+            match self.peek() {
+                Some("_") => {
+                    let next_is_digit = self
+                        .peek_next()
+                        .is_some_and(|c| is_digit_in_radix(c, radix));
+                    // will never be None
+                    self.advance();
+                    if !seen || !next_is_digit {
+                        return Err("'_' can only separate digits");
+                    }
+                }
+                Some(c) if is_digit_in_radix(c, radix) => {
+                    seen = true;
+                    // will never be None
+                    self.advance();
+                }
+                _ => return Ok(seen),
+            }
+        }
+    }
+
+    fn identifier(&mut self) {
+        self.advance_while(is_alphanumeric);
+
+        let text = take_slice(self.text, &self.source, self.start, self.current);
+        // `café` typed with a precomposed or a combining accent is the same name
+        let normalized = match is_nfc_quick(text.chars()) {
+            IsNormalized::Yes => None,
+            _ => Some(text.nfc().collect::<String>()).filter(|nfc| nfc != text),
+        };
+        let token_type = match normalized.as_deref().unwrap_or(text) {
+            "and" => TokenType::AND,
+            "class" => TokenType::CLASS,
+            "else" => TokenType::ELSE,
+            "false" => TokenType::FALSE,
+            "for" => TokenType::FOR,
+            "fun" => TokenType::FUN,
+            "if" => TokenType::IF,
+            "nil" => TokenType::NIL,
+            "or" => TokenType::OR,
+            "print" => TokenType::PRINT,
+            "return" => TokenType::RETURN,
+            "super" => TokenType::SUPER,
+            "this" => TokenType::THIS,
+            "true" => TokenType::TRUE,
+            "var" => TokenType::VAR,
+            "while" => TokenType::WHILE,
+            _ => TokenType::IDENTIFIER,
+        };
+        self.add_token(token_type);
+        if let Some(token) = self.tokens.last_mut() {
+            token.normalized = normalized.map(String::into_boxed_str);
+        }
+    }
+}
+
+// "\r\n" is segmented as a single grapheme cluster
+fn is_newline(c: &str) -> bool {
+    c == "\n" || c == "\r\n"
+}
+
+fn is_digit(c: &str) -> bool {
+    c.chars().all(|c| c.is_ascii_digit())
+}
+
+// strips the indentation and leading `*` that usually decorate the lines of a /** */ comment
+fn block_doc(text: &str) -> String {
+    let lines = text
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect::<Vec<_>>();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+fn is_digit_in_radix(c: &str, radix: u32) -> bool {
+    let mut chars = c.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_digit(radix))
+}
+
+// identifiers follow UAX #31: an XID_Start character or '_', then XID_Continue characters
+fn is_alpha(c: &str) -> bool {
+    let mut chars = c.chars();
+    chars
+        .next()
+        .is_some_and(|c| unicode_ident::is_xid_start(c) || c == '_')
+        && chars.all(unicode_ident::is_xid_continue)
+}
+
+fn is_alphanumeric(c: &str) -> bool {
+    c.chars().all(unicode_ident::is_xid_continue)
+}
@@ -0,0 +1,73 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rlox::read_scanner::ReadScanner;
+use rlox::scanner::Scanner;
+use std::hint::black_box;
+
+// the baseline is a copy of the scanner that split the whole source into grapheme clusters
+// up front, so both are measured on the same source and machine
+mod baseline;
+
+// a few megabytes of generated Lox exercising every kind of token, comment and literal
+fn generated_source() -> String {
+    let mut source = String::new();
+    for i in 0..20_000 {
+        source.push_str(&format!(
+            "/// Returns the {i}th value.\n\
+             fun value_{i}(a, b) {{\n    \
+                 // plain comment\n    \
+                 var café_{i} = a * {i}.5 + b / 0x{i:x} - 1_000e-3;\n    \
+                 /* block /* nested */ comment */\n    \
+                 if (a >= b and !(a == nil)) {{ print \"{i}: ${{a}} \\t ok\"; }}\n    \
+                 return café_{i};\n\
+             }}\n"
+        ));
+    }
+    source
+}
+
+fn scan(c: &mut Criterion) {
+    let source = generated_source();
+    let mut group = c.benchmark_group("scanner");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.sample_size(10);
+    group.bench_function("baseline_scan_tokens", |b| {
+        b.iter(|| {
+            baseline::Scanner::new(black_box(&source))
+                .scan_tokens()
+                .unwrap()
+        })
+    });
+    group.bench_function("baseline_scan_tokens_with_trivia", |b| {
+        b.iter(|| {
+            baseline::Scanner::new(black_box(&source))
+                .with_trivia()
+                .scan_tokens()
+                .unwrap()
+        })
+    });
+    group.bench_function("scan_tokens", |b| {
+        b.iter(|| Scanner::new(black_box(&source)).scan_tokens().unwrap())
+    });
+    group.bench_function("scan_tokens_with_trivia", |b| {
+        b.iter(|| {
+            Scanner::new(black_box(&source))
+                .with_trivia()
+                .scan_tokens()
+                .unwrap()
+        })
+    });
+    group.bench_function("read_scanner", |b| {
+        b.iter(|| {
+            let mut scanner = ReadScanner::new(black_box(source.as_bytes()));
+            let mut count = 0;
+            while let Some((tokens, _)) = scanner.next_chunk().unwrap() {
+                count += tokens.len();
+            }
+            count
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
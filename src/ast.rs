@@ -0,0 +1,174 @@
+use crate::expr::Expr;
+use crate::span::Span;
+use crate::stmt::{Function, Stmt};
+use std::marker::PhantomData;
+use std::ops::Index;
+
+// identifies a node by its index in the arena of its kind
+pub trait NodeId: Copy {
+    fn index(self) -> usize;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StmtId(u32);
+
+impl NodeId for ExprId {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl NodeId for StmtId {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// every expression and statement of a program, each in an arena of its own kind. Nodes
+// refer to their children by id, and ids stay valid for as long as the `Ast` lives.
+#[derive(Debug, Clone, Default)]
+pub struct Ast<'a> {
+    exprs: Vec<Expr<'a>>,
+    stmts: Vec<Stmt<'a>>,
+    // the top-level statements of the program, in source order
+    statements: Vec<StmtId>,
+}
+
+impl<'a> Ast<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn statements(&self) -> &[StmtId] {
+        &self.statements
+    }
+
+    pub fn exprs(&self) -> impl Iterator<Item = (ExprId, &Expr<'a>)> {
+        self.exprs
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| (ExprId(i as u32), expr))
+    }
+
+    pub fn stmts(&self) -> impl Iterator<Item = (StmtId, &Stmt<'a>)> {
+        self.stmts
+            .iter()
+            .enumerate()
+            .map(|(i, stmt)| (StmtId(i as u32), stmt))
+    }
+
+    pub fn add_expr(&mut self, expr: Expr<'a>) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+
+    pub fn add_stmt(&mut self, stmt: Stmt<'a>) -> StmtId {
+        self.stmts.push(stmt);
+        StmtId(self.stmts.len() as u32 - 1)
+    }
+
+    pub fn expr_mut(&mut self, id: ExprId) -> &mut Expr<'a> {
+        &mut self.exprs[id.index()]
+    }
+
+    pub fn stmt_mut(&mut self, id: StmtId) -> &mut Stmt<'a> {
+        &mut self.stmts[id.index()]
+    }
+
+    pub fn set_statements(&mut self, statements: Vec<StmtId>) {
+        self.statements = statements;
+    }
+
+    pub(crate) fn push_statement(&mut self, id: StmtId) {
+        self.statements.push(id);
+    }
+
+    // the function a `Function` statement declares
+    pub fn function(&self, id: StmtId) -> Option<&Function<'a>> {
+        match &self[id] {
+            Stmt::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self[id].span(self)
+    }
+
+    pub fn stmt_span(&self, id: StmtId) -> Span {
+        self[id].span()
+    }
+
+    // prints an expression in the parenthesised form the REPL and error messages use
+    pub fn display(&self, id: ExprId) -> Displayed<'_, 'a> {
+        Displayed { ast: self, id }
+    }
+
+    // a copy of the tree that no longer borrows the source; ids are unchanged
+    pub fn into_owned(self) -> Ast<'static> {
+        Ast {
+            exprs: self.exprs.into_iter().map(Expr::into_owned).collect(),
+            stmts: self.stmts.into_iter().map(Stmt::into_owned).collect(),
+            statements: self.statements,
+        }
+    }
+}
+
+impl<'a> Index<ExprId> for Ast<'a> {
+    type Output = Expr<'a>;
+
+    fn index(&self, id: ExprId) -> &Expr<'a> {
+        &self.exprs[id.index()]
+    }
+}
+
+impl<'a> Index<StmtId> for Ast<'a> {
+    type Output = Stmt<'a>;
+
+    fn index(&self, id: StmtId) -> &Stmt<'a> {
+        &self.stmts[id.index()]
+    }
+}
+
+pub struct Displayed<'t, 'a> {
+    pub(crate) ast: &'t Ast<'a>,
+    pub(crate) id: ExprId,
+}
+
+// results of an analysis, one slot per node of one kind, kept apart from the `Ast` so that
+// passes can attach them without touching the tree
+#[derive(Debug, Clone)]
+pub struct SideTable<I, T> {
+    values: Vec<Option<T>>,
+    id: PhantomData<I>,
+}
+
+impl<I: NodeId, T> Default for SideTable<I, T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            id: PhantomData,
+        }
+    }
+}
+
+impl<I: NodeId, T> SideTable<I, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, id: I, value: T) {
+        let index = id.index();
+        if index >= self.values.len() {
+            self.values.resize_with(index + 1, || None);
+        }
+        self.values[index] = Some(value);
+    }
+
+    pub fn get(&self, id: I) -> Option<&T> {
+        self.values.get(id.index())?.as_ref()
+    }
+}
@@ -1,49 +1,269 @@
 use anyhow::Result;
-use rlox::interpreter::Interpreter;
-use rlox::parser::Parser;
-use rlox::scanner::Scanner;
-use std::io::{stdin, stdout, Write};
+use rlox::ast::{Ast, ExprId, SideTable};
+use rlox::diagnostics::{Diagnostic, Renderer};
+use rlox::dump;
+use rlox::formatter::{format_source, BraceStyle, FormatConfig};
+use rlox::interpreter::{Interpreter, Program, RuntimeError};
+use rlox::parser::{Parser, ParserError};
+use rlox::read_scanner::ReadScanner;
+use rlox::resolver::{Resolver, ResolverError};
+use rlox::scanner::{confusable_identifiers, Confusables, ScanError, ScanWarning, Scanner};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::fs::File;
+use std::io::{stderr, stdin, stdout, IsTerminal, Write};
+use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum Error {
+    #[error("ScannerError")]
+    ScannerError,
     #[error("ParserError")]
     ParserError,
+    #[error("ResolverError")]
+    ResolverError,
     #[error("RuntimeError")]
     RuntimeError,
 }
 
+// renders diagnostics for one piece of source into a sink, which is stderr unless the
+// interpreter was given another
+struct Reporter<'s> {
+    name: &'s str,
+    source: OnceCell<Cow<'s, str>>,
+    renderer: Renderer,
+}
+
+impl<'s> Reporter<'s> {
+    fn new(name: &'s str, source: &'s str) -> Self {
+        let reporter = Self::streamed(name);
+        let _ = reporter.source.set(Cow::Borrowed(source));
+        reporter
+    }
+
+    // for a file that is scanned as it is read, which is only read whole again if there is
+    // a diagnostic to show against it
+    fn streamed(path: &'s str) -> Self {
+        Self {
+            name: path,
+            source: OnceCell::new(),
+            renderer: stderr_renderer(),
+        }
+    }
+
+    fn source(&self) -> &str {
+        self.source
+            .get_or_init(|| Cow::Owned(std::fs::read_to_string(self.name).unwrap_or_default()))
+    }
+
+    // a sink that can't take a diagnostic has nowhere better to report that to, so the
+    // failure is dropped
+    fn report(&self, diagnostic: &Diagnostic, out: &mut dyn Write) {
+        let _ = write!(
+            out,
+            "{}",
+            self.renderer.render(diagnostic, self.name, self.source())
+        );
+    }
+}
+
+// colours diagnostics only when they go to a terminal and NO_COLOR isn't set
+fn stderr_renderer() -> Renderer {
+    if stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        Renderer::coloured()
+    } else {
+        Renderer::plain()
+    }
+}
+
+const USAGE: &str = "Usage: rlox [script]
+       rlox tokens <file>
+       rlox ast [--format=sexpr|json] <file>
+       rlox fmt [--check] [--indent-width=N] [--max-width=N] \
+[--brace-style=same-line|next-line] <files>";
+
+// room for `DEFAULT_MAX_CALL_DEPTH` calls, whose frames are far larger in a debug build
+const PROGRAM_STACK_SIZE: usize = if cfg!(debug_assertions) {
+    1 << 30
+} else {
+    64 << 20
+};
+
+fn usage() -> ! {
+    println!("{}", USAGE);
+    std::process::exit(64);
+}
+
 fn main() -> Result<()> {
     let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("fmt") => return run_fmt(&args[2..]),
+        Some("tokens") => return run_tokens(&args[2..]),
+        Some("ast") => return run_ast(&args[2..]),
+        _ => {}
+    }
     if args.len() > 2 {
-        println!("Usage: rlox [script]");
-        std::process::exit(64);
-    } else if args.len() == 2 {
-        run_file(&args[1])?;
-    } else {
-        run_prompt()?;
+        usage();
     }
-    Ok(())
+    // every call in a script nests several native frames, so programs run on a thread with
+    // enough stack for the interpreter to stop runaway recursion itself
+    let program = std::thread::Builder::new()
+        .stack_size(PROGRAM_STACK_SIZE)
+        .spawn(move || match args.get(1) {
+            Some(path) => run_file(path),
+            None => run_prompt(),
+        })?;
+    program.join().unwrap_or_else(|_| std::process::exit(101))
 }
 
+// the parser pulls tokens as the file is read, so neither the whole source nor all of its
+// tokens are held at once. Nothing runs until every syntax error has been found, though,
+// so the tree is built in full first.
 fn run_file(path: &str) -> Result<()> {
-    let interpreter = Interpreter::new();
-    let source = std::fs::read_to_string(path)?;
-    if let Err(e) = run(&source, &interpreter) {
+    let reporter = Reporter::streamed(path);
+    let mut scanner = ReadScanner::new(File::open(path)?);
+    // scanning carries on past errors so that the parser can report its own in the same run
+    let mut errors = Vec::new();
+    let mut confusables = Confusables::default();
+    let mut warnings = Vec::new();
+    let tokens = scanner
+        .by_ref()
+        .filter_map(|item| item.map_err(|e| errors.push(e)).ok())
+        .inspect(|token| warnings.extend(confusables.check(token)));
+    let parsed = Parser::new(tokens).parse_program();
+    if let Some(e) = scanner.take_error() {
+        return Err(e.into());
+    }
+
+    let mut interpreter = Interpreter::new().with_renderer(stderr_renderer());
+    let parse_errors = parsed.as_ref().err().map_or(&[][..], Vec::as_slice);
+    let diagnostics = front_end_diagnostics(&errors, &warnings, parse_errors);
+    report_all(&diagnostics, &reporter, interpreter.diagnostics());
+    let result = checked(parsed, &errors).and_then(|ast| {
+        let locals = resolve(&ast, &reporter, interpreter.diagnostics())?;
+        interpret(Program::new(ast, locals), &mut interpreter, &reporter)
+    });
+    if let Err(e) = result {
         match e {
-            Error::ParserError => std::process::exit(65),
+            Error::ScannerError | Error::ParserError | Error::ResolverError => {
+                std::process::exit(65)
+            }
             Error::RuntimeError => std::process::exit(70),
         }
     }
     Ok(())
 }
 
+// prints every token of a file with where it starts
+fn run_tokens(args: &[String]) -> Result<()> {
+    let [path] = args else { usage() };
+    let source = std::fs::read_to_string(path)?;
+    let reporter = Reporter::new(path, &source);
+    let (tokens, errors) = Scanner::new(&source).scan_partial();
+    for token in &tokens {
+        println!("{}:{} {}", token.span.line, token.span.column, token);
+    }
+    if report_scan_errors(&errors, &reporter, &mut stderr()).is_err() {
+        std::process::exit(65);
+    }
+    Ok(())
+}
+
+// prints the tree a file parses to, as S-expressions or as JSON for other tools
+fn run_ast(args: &[String]) -> Result<()> {
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.strip_prefix("--format=") {
+            Some("sexpr") => json = false,
+            Some("json") => json = true,
+            Some(_) => usage(),
+            None if arg.starts_with("--") => usage(),
+            None => paths.push(arg),
+        }
+    }
+    let [path] = paths.as_slice() else { usage() };
+    let source = std::fs::read_to_string(path)?;
+    let reporter = Reporter::new(path, &source);
+    let (tokens, errors) = Scanner::new(&source).scan_partial();
+    let parsed = Parser::new(tokens).parse_program();
+    let parse_errors = parsed.as_ref().err().map_or(&[][..], Vec::as_slice);
+    let diagnostics = front_end_diagnostics(&errors, &[], parse_errors);
+    report_all(&diagnostics, &reporter, &mut stderr());
+    let Ok(ast) = checked(parsed, &errors) else {
+        std::process::exit(65);
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&dump::to_json(&ast))?);
+    } else {
+        print!("{}", dump::to_sexpr(&ast));
+    }
+    Ok(())
+}
+
+// formats files in place, or with `--check` only reports those that would change
+fn run_fmt(args: &[String]) -> Result<()> {
+    let mut check = false;
+    let mut config = FormatConfig::default();
+    let mut paths = Vec::new();
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else if let Some(width) = arg.strip_prefix("--indent-width=") {
+            config = config.with_indent_width(width.parse().unwrap_or_else(|_| usage()));
+        } else if let Some(width) = arg.strip_prefix("--max-width=") {
+            config = config.with_max_width(width.parse().unwrap_or_else(|_| usage()));
+        } else if let Some(style) = arg.strip_prefix("--brace-style=") {
+            config = config.with_brace_style(match style {
+                "same-line" => BraceStyle::SameLine,
+                "next-line" => BraceStyle::NextLine,
+                _ => usage(),
+            });
+        } else if arg.starts_with("--") {
+            usage();
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.is_empty() {
+        usage();
+    }
+
+    let mut unformatted = false;
+    let mut failed = false;
+    for path in paths {
+        let source = std::fs::read_to_string(path)?;
+        match format_source(&source, &config) {
+            Ok(formatted) if formatted == source => {}
+            Ok(_) if check => {
+                eprintln!("{} is not formatted", path);
+                unformatted = true;
+            }
+            Ok(formatted) => std::fs::write(path, formatted)?,
+            Err(diagnostics) => {
+                let reporter = Reporter::new(path, &source);
+                for diagnostic in &diagnostics {
+                    reporter.report(diagnostic, &mut stderr());
+                }
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(65);
+    } else if unformatted {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn run_prompt() -> Result<()> {
     println!("Welcome to 🐟rlox🐟 REPL!");
     let mut error = false;
     let prefix = "🐟> ";
     let bad_prefix = "😵> ";
-    let interpreter = Interpreter::new();
+    let mut interpreter = Interpreter::new().with_renderer(stderr_renderer());
     loop {
         print!("{}", if !error { prefix } else { bad_prefix });
         stdout().flush()?;
@@ -52,7 +272,7 @@ fn run_prompt() -> Result<()> {
         if line.is_empty() {
             break;
         }
-        match run(&line, &interpreter) {
+        match run_line(line, &mut interpreter) {
             Ok(_) => error = false,
             Err(_) => error = true,
         }
@@ -60,25 +280,129 @@ fn run_prompt() -> Result<()> {
     Ok(())
 }
 
-fn run(source: &str, interpreter: &Interpreter) -> Result<(), Error> {
-    let scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
-    // for token in tokens.iter() {
-    //     println!("{}", token);
-    // }
+// functions defined on one line are called from later ones, so every line's program is
+// made to own its tokens before it runs and can outlive the line
+fn run_line(line: String, interpreter: &mut Interpreter<'static>) -> Result<(), Error> {
+    let reporter = Reporter::new("<repl>", &line);
+    let (tokens, errors) = Scanner::new(&line).scan_partial();
+    let warnings = confusable_identifiers(&tokens);
 
-    let parser = Parser::new(&tokens);
-    let expression = parser.parse().map_err(|e| {
-        eprintln!("ParserError: {e}");
-        Error::ParserError
-    })?;
-    // println!("{}", expression);
+    // the REPL also accepts a bare expression without the trailing ';' and echoes its value
+    let expression = Parser::new(tokens.iter().cloned()).parse();
+    if let (Ok((ast, expression)), true) = (expression, errors.is_empty()) {
+        for warning in &warnings {
+            reporter.report(&warning.to_diagnostic(), interpreter.diagnostics());
+        }
+        let ast = ast.into_owned();
+        let locals = Resolver::new(&ast)
+            .resolve_expression(expression)
+            .map_err(|errors| {
+                report_resolve_errors(errors, &reporter, interpreter.diagnostics())
+            })?;
+        let program = Rc::new(Program::new(ast, locals));
+        let value = interpreter
+            .evaluate(program, expression)
+            .map_err(|e| report_runtime_error(e, &reporter, interpreter))?;
+        let _ = writeln!(interpreter.output(), "{}", value);
+        return Ok(());
+    }
 
-    let value = interpreter.interpret(&expression).map_err(|e| {
-        eprintln!("RuntimeError: {e}");
-        Error::RuntimeError
-    })?;
-    println!("{}", value);
+    let parsed = Parser::new(tokens).parse_program();
+    let parse_errors = parsed.as_ref().err().map_or(&[][..], Vec::as_slice);
+    let diagnostics = front_end_diagnostics(&errors, &warnings, parse_errors);
+    report_all(&diagnostics, &reporter, interpreter.diagnostics());
+    let ast = checked(parsed, &errors)?.into_owned();
+    let locals = resolve(&ast, &reporter, interpreter.diagnostics())?;
+    interpret(Program::new(ast, locals), interpreter, &reporter)
+}
 
-    Ok(())
+fn report_scan_errors(
+    errors: &[ScanError],
+    reporter: &Reporter,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    for e in errors {
+        reporter.report(&e.to_diagnostic(), out);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ScannerError)
+    }
+}
+
+// what scanning and parsing a piece of source found wrong with it, in source order. The
+// sort is stable, so a scan error comes before a parse error it leads to at the same place.
+fn front_end_diagnostics(
+    scan_errors: &[ScanError],
+    warnings: &[ScanWarning],
+    parse_errors: &[ParserError],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = scan_errors
+        .iter()
+        .map(ScanError::to_diagnostic)
+        .chain(warnings.iter().map(ScanWarning::to_diagnostic))
+        .chain(parse_errors.iter().map(ParserError::to_diagnostic))
+        .collect::<Vec<_>>();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.primary.as_ref().map(|label| label.span.start));
+    diagnostics
+}
+
+fn report_all(diagnostics: &[Diagnostic], reporter: &Reporter, out: &mut dyn Write) {
+    for diagnostic in diagnostics {
+        reporter.report(diagnostic, out);
+    }
+}
+
+// the tree, as long as neither scanning nor parsing failed
+fn checked<'a>(
+    parsed: Result<Ast<'a>, Vec<ParserError<'a>>>,
+    scan_errors: &[ScanError],
+) -> Result<Ast<'a>, Error> {
+    let ast = parsed.map_err(|_| Error::ParserError)?;
+    if scan_errors.is_empty() {
+        Ok(ast)
+    } else {
+        Err(Error::ScannerError)
+    }
+}
+
+fn resolve(
+    ast: &Ast,
+    reporter: &Reporter,
+    out: &mut dyn Write,
+) -> Result<SideTable<ExprId, usize>, Error> {
+    Resolver::new(ast)
+        .resolve()
+        .map_err(|errors| report_resolve_errors(errors, reporter, out))
+}
+
+fn report_resolve_errors(
+    errors: Vec<ResolverError>,
+    reporter: &Reporter,
+    out: &mut dyn Write,
+) -> Error {
+    for e in errors {
+        reporter.report(&e.to_diagnostic(), out);
+    }
+    Error::ResolverError
+}
+
+fn interpret<'a>(
+    program: Program<'a>,
+    interpreter: &mut Interpreter<'a>,
+    reporter: &Reporter,
+) -> Result<(), Error> {
+    interpreter
+        .interpret(Rc::new(program))
+        .map_err(|e| report_runtime_error(e, reporter, interpreter))
+}
+
+fn report_runtime_error(
+    e: RuntimeError,
+    reporter: &Reporter,
+    interpreter: &mut Interpreter,
+) -> Error {
+    interpreter.report(&e, reporter.name, reporter.source());
+    Error::RuntimeError
 }
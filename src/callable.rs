@@ -0,0 +1,154 @@
+use crate::ast::StmtId;
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, InterpreterResult, Program, Unwind, Value};
+use crate::stmt;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub enum Callable<'a> {
+    Function(Rc<LoxFunction<'a>>),
+    Native(Rc<NativeFunction>),
+}
+
+impl<'a> Callable<'a> {
+    pub fn arity(&self) -> usize {
+        match self {
+            Callable::Function(function) => function.arity(),
+            Callable::Native(native) => native.arity,
+        }
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> InterpreterResult<Value<'a>> {
+        match self {
+            Callable::Function(function) => function.call(interpreter, arguments),
+            Callable::Native(native) => (native.function)(&arguments),
+        }
+    }
+}
+
+impl<'a> PartialEq for Callable<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Callable::Function(l), Callable::Function(r)) => Rc::ptr_eq(l, r),
+            (Callable::Native(l), Callable::Native(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for Callable<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Callable::Function(function) => write!(f, "<fn {}>", function.name()),
+            Callable::Native(_) => write!(f, "<native fn>"),
+        }
+    }
+}
+
+pub struct LoxFunction<'a> {
+    program: Rc<Program<'a>>,
+    // a `Function` statement of the program
+    declaration: StmtId,
+    closure: Rc<RefCell<Environment<'a>>>,
+    is_initializer: bool,
+}
+
+impl<'a> LoxFunction<'a> {
+    pub fn new(
+        program: Rc<Program<'a>>,
+        declaration: StmtId,
+        closure: Rc<RefCell<Environment<'a>>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            program,
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    fn declaration(&self) -> &stmt::Function<'a> {
+        self.program
+            .ast
+            .function(self.declaration)
+            .expect("functions are declared by function statements")
+    }
+
+    pub fn name(&self) -> &str {
+        self.declaration().name.name()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration().params.len()
+    }
+
+    // wraps the closure in a scope where `this` is the given instance
+    pub fn bind(&self, instance: Value<'a>) -> LoxFunction<'a> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this", instance);
+        LoxFunction::new(
+            self.program.clone(),
+            self.declaration,
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    pub(crate) fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> InterpreterResult<Value<'a>> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        let declaration = self.declaration();
+        for (param, argument) in declaration.params.iter().zip(arguments) {
+            environment.define(param.name(), argument);
+        }
+
+        let value = match interpreter.execute_body(
+            &self.program,
+            &declaration.body,
+            Rc::new(RefCell::new(environment)),
+        ) {
+            Ok(()) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(e)) => return Err(e),
+        };
+
+        // an initializer always hands back the instance, even on a bare `return;`
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .get_at(0, "this")
+                .unwrap_or(Value::Nil));
+        }
+        Ok(value)
+    }
+}
+
+// the closure may hold the function itself, so only the name is printed
+impl<'a> std::fmt::Debug for LoxFunction<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeFunction {
+    pub arity: usize,
+    pub function: for<'a> fn(&[Value<'a>]) -> InterpreterResult<Value<'a>>,
+}
+
+pub fn clock<'a>(_arguments: &[Value<'a>]) -> InterpreterResult<Value<'a>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(now.as_secs_f64()))
+}
@@ -0,0 +1,115 @@
+use crate::callable::{Callable, LoxFunction};
+use crate::interpreter::{InterpreterResult, RuntimeError, Value};
+use crate::token::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub struct LoxClass<'a> {
+    pub name: String,
+    superclass: Option<Rc<LoxClass<'a>>>,
+    methods: HashMap<String, Rc<LoxFunction<'a>>>,
+}
+
+impl<'a> LoxClass<'a> {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass<'a>>>,
+        methods: HashMap<String, Rc<LoxFunction<'a>>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction<'a>>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+}
+
+// classes compare by identity
+impl<'a> PartialEq for LoxClass<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl<'a> std::fmt::Debug for LoxClass<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl<'a> std::fmt::Display for LoxClass<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance<'a> {
+    pub class: Rc<LoxClass<'a>>,
+    fields: HashMap<String, Value<'a>>,
+}
+
+impl<'a> LoxInstance<'a> {
+    pub fn new(class: Rc<LoxClass<'a>>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value<'a>) {
+        self.fields.insert(name.name().to_string(), value);
+    }
+}
+
+// fields shadow methods; methods come back bound to the instance they were looked up on
+pub fn get<'a>(
+    instance: &Rc<RefCell<LoxInstance<'a>>>,
+    name: &Token,
+) -> InterpreterResult<Value<'a>> {
+    if let Some(value) = instance.borrow().fields.get(name.name()) {
+        return Ok(value.clone());
+    }
+
+    let method = instance.borrow().class.find_method(name.name());
+    match method {
+        Some(method) => Ok(Value::Callable(Callable::Function(Rc::new(
+            method.bind(Value::Instance(instance.clone())),
+        )))),
+        None => Err(RuntimeError::new(
+            format!("Undefined property '{}'", name.name()),
+            name.span,
+        )),
+    }
+}
+
+// instances compare by identity
+impl<'a> PartialEq for LoxInstance<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// fields may refer back to the instance, so only the class name is printed
+impl<'a> std::fmt::Debug for LoxInstance<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+impl<'a> std::fmt::Display for LoxInstance<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,235 @@
+use crate::span::Span;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn colour(self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// a problem found in a piece of source, with everything needed to point at it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub primary: Option<Label>,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.primary = Some(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+// the one-line form, for when the source isn't at hand
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(primary) = &self.primary {
+            write!(f, " at {}", primary.span)?;
+        }
+        Ok(())
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+const TAB_WIDTH: usize = 4;
+
+// renders diagnostics against their source, underlining each labelled span
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    colour: bool,
+}
+
+impl Renderer {
+    pub fn plain() -> Self {
+        Self { colour: false }
+    }
+
+    pub fn coloured() -> Self {
+        Self { colour: true }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, name: &str, source: &str) -> String {
+        let mut out = String::new();
+
+        let severity_colour = diagnostic.severity.colour();
+        out += &self.paint(severity_colour, &diagnostic.severity.to_string());
+        if let Some(code) = diagnostic.code {
+            out += &self.paint(severity_colour, &format!("[{}]", code));
+        }
+        out += &self.paint(BOLD, &format!(": {}", diagnostic.message));
+        out += "\n";
+
+        let labels = diagnostic
+            .primary
+            .iter()
+            .map(|label| (label, '^', severity_colour))
+            .chain(diagnostic.secondary.iter().map(|label| (label, '-', BLUE)))
+            .collect::<Vec<_>>();
+        let gutter = labels
+            .iter()
+            .map(|(label, _, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        if let Some(primary) = &diagnostic.primary {
+            out += &format!(
+                "{}{} {}:{}:{}\n",
+                " ".repeat(gutter),
+                self.paint(BLUE, "-->"),
+                name,
+                primary.span.line,
+                primary.span.column
+            );
+        }
+        if !labels.is_empty() {
+            out += &format!("{}\n", self.margin(gutter, None));
+        }
+        for (label, marker, colour) in labels {
+            let (line, prefix, underlined) = snippet(source, label.span);
+            out += &format!("{} {}\n", self.margin(gutter, Some(label.span.line)), line);
+            let underline = format!(
+                "{} {}",
+                marker.to_string().repeat(underlined.max(1)),
+                label.message
+            );
+            out += &format!(
+                "{} {}{}\n",
+                self.margin(gutter, None),
+                " ".repeat(prefix),
+                self.paint(colour, underline.trim_end())
+            );
+        }
+        for note in &diagnostic.notes {
+            out += &format!(
+                "{} = {}: {}\n",
+                " ".repeat(gutter),
+                self.paint(BOLD, "note"),
+                note
+            );
+        }
+        out
+    }
+
+    // the left margin of a snippet line, holding the line number when there is one
+    fn margin(&self, width: usize, line: Option<usize>) -> String {
+        let number = line.map_or(String::new(), |line| line.to_string());
+        self.paint(BLUE, &format!("{:>width$} |", number))
+    }
+
+    fn paint(&self, colour: &str, text: &str) -> String {
+        if self.colour {
+            format!("{}{}{}", colour, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// returns the line containing the start of `span` with tabs expanded, the display width
+// before the span, and the display width of the part of the span on that line
+fn snippet(source: &str, span: Span) -> (String, usize, usize) {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let line = source[line_start..line_end].trim_end_matches('\r');
+
+    let prefix = width(&source[line_start..start]);
+    let end = span.end.clamp(start, line_start + line.len());
+    let underlined = width(&source[start..end]);
+    (expand_tabs(line), prefix, underlined)
+}
+
+fn width(text: &str) -> usize {
+    text.graphemes(true)
+        .map(|g| if g == "\t" { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
@@ -0,0 +1,426 @@
+use crate::ast::{Ast, ExprId, StmtId};
+use crate::expr::Expr;
+use crate::span::Span;
+use crate::stmt::{Function, Stmt};
+use crate::token::Token;
+use crate::token_type::Literal;
+use crate::visit::{ExprVisitor, StmtVisitor};
+use serde_json::{json, Value};
+
+// bumped whenever a kind or field is renamed or removed, so that tools can tell which shape
+// of tree they are reading; new fields may appear without a bump
+pub const JSON_VERSION: u32 = 1;
+
+// the program as S-expressions, one top-level statement per line
+pub fn to_sexpr(ast: &Ast) -> String {
+    let mut output = String::new();
+    for statement in Sexpr.visit_program(ast) {
+        output.push_str(&statement);
+        output.push('\n');
+    }
+    output
+}
+
+// prints each node as an S-expression
+struct Sexpr;
+
+impl Sexpr {
+    fn list(head: &str, items: Vec<String>) -> String {
+        let mut text = format!("({}", head);
+        for item in items {
+            text.push(' ');
+            text.push_str(&item);
+        }
+        text.push(')');
+        text
+    }
+
+    fn stmts(&mut self, ast: &Ast, ids: &[StmtId]) -> Vec<String> {
+        ids.iter().map(|&id| self.visit_stmt(ast, id)).collect()
+    }
+
+    fn exprs(&mut self, ast: &Ast, ids: &[ExprId]) -> String {
+        ids.iter()
+            .map(|&id| format!(" {}", self.visit_expr(ast, id)))
+            .collect()
+    }
+}
+
+impl<'t, 'a: 't> StmtVisitor<'t, 'a, String> for Sexpr {
+    fn visit_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) -> String {
+        match &ast[id] {
+            Stmt::Block { statements, .. } => Self::list("block", self.stmts(ast, statements)),
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                ..
+            } => {
+                let mut items = vec![name.lexeme.to_string()];
+                if let Some(super_class) = *super_class {
+                    items.push(format!("(< {})", self.visit_expr(ast, super_class)));
+                }
+                items.extend(self.stmts(ast, methods));
+                Self::list("class", items)
+            }
+            Stmt::Expression { expression, .. } => {
+                Self::list("expr", vec![self.visit_expr(ast, *expression)])
+            }
+            Stmt::Function(function) => {
+                let params = function
+                    .params
+                    .iter()
+                    .map(|param| param.lexeme.as_ref())
+                    .collect::<Vec<_>>();
+                let mut items = vec![
+                    function.name.lexeme.to_string(),
+                    format!("({})", params.join(" ")),
+                ];
+                items.extend(self.stmts(ast, &function.body));
+                Self::list("fun", items)
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut items = vec![
+                    self.visit_expr(ast, *condition),
+                    self.visit_stmt(ast, *then_branch),
+                ];
+                items.extend(else_branch.map(|else_branch| self.visit_stmt(ast, else_branch)));
+                Self::list("if", items)
+            }
+            Stmt::Print { expression, .. } => {
+                Self::list("print", vec![self.visit_expr(ast, *expression)])
+            }
+            Stmt::Return { value, .. } => Self::list(
+                "return",
+                value
+                    .map(|value| self.visit_expr(ast, value))
+                    .into_iter()
+                    .collect(),
+            ),
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                let mut items = vec![name.lexeme.to_string()];
+                items.extend(initializer.map(|initializer| self.visit_expr(ast, initializer)));
+                Self::list("var", items)
+            }
+            Stmt::While {
+                condition, body, ..
+            } => Self::list(
+                "while",
+                vec![
+                    self.visit_expr(ast, *condition),
+                    self.visit_stmt(ast, *body),
+                ],
+            ),
+        }
+    }
+}
+
+impl<'t, 'a: 't> ExprVisitor<'t, 'a, String> for Sexpr {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) -> String {
+        match &ast[id] {
+            Expr::Assign { name, value } => {
+                format!("(= {} {})", name.lexeme, self.visit_expr(ast, *value))
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => format!(
+                "({} {} {})",
+                operator.lexeme,
+                self.visit_expr(ast, *left),
+                self.visit_expr(ast, *right)
+            ),
+            Expr::Call {
+                callee, arguments, ..
+            } => format!(
+                "(call {}{})",
+                self.visit_expr(ast, *callee),
+                self.exprs(ast, arguments)
+            ),
+            Expr::Get { object, name } => {
+                format!("(. {} {})", self.visit_expr(ast, *object), name.lexeme)
+            }
+            Expr::Grouping { expression, .. } => {
+                format!("(group {})", self.visit_expr(ast, *expression))
+            }
+            Expr::Interpolation { parts, .. } => {
+                format!("(interpolation{})", self.exprs(ast, parts))
+            }
+            Expr::Literal { value, .. } => match value {
+                Literal::String(s) => format!("{:?}", s),
+                _ => value.to_string(),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => format!(
+                "(.= {} {} {})",
+                self.visit_expr(ast, *object),
+                name.lexeme,
+                self.visit_expr(ast, *value)
+            ),
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::This { .. } => "this".to_string(),
+            Expr::Unary { operator, right } => {
+                format!("({} {})", operator.lexeme, self.visit_expr(ast, *right))
+            }
+            Expr::Variable { name } => name.lexeme.to_string(),
+        }
+    }
+}
+
+// the program as a JSON document of the form `{"version": 1, "statements": [...]}`. Every
+// node is an object with its `kind` and `span` alongside its fields; child nodes are nested
+// in place and tokens are objects with their `lexeme` and `span`. A number literal's value is
+// a JSON number, or the string `"inf"` when it is too large to be finite.
+pub fn to_json(ast: &Ast) -> Value {
+    json!({
+        "version": JSON_VERSION,
+        "statements": Json.visit_program(ast),
+    })
+}
+
+// builds each node as a JSON object
+struct Json;
+
+impl Json {
+    fn stmts(&mut self, ast: &Ast, ids: &[StmtId]) -> Vec<Value> {
+        ids.iter().map(|&id| self.visit_stmt(ast, id)).collect()
+    }
+
+    fn exprs(&mut self, ast: &Ast, ids: &[ExprId]) -> Vec<Value> {
+        ids.iter().map(|&id| self.visit_expr(ast, id)).collect()
+    }
+}
+
+impl<'t, 'a: 't> StmtVisitor<'t, 'a, Value> for Json {
+    fn visit_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) -> Value {
+        let span = span_json(ast.stmt_span(id));
+        match &ast[id] {
+            Stmt::Block { statements, .. } => json!({
+                "kind": "Block",
+                "span": span,
+                "statements": self.stmts(ast, statements),
+            }),
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                doc,
+                ..
+            } => json!({
+                "kind": "Class",
+                "span": span,
+                "name": token_json(name),
+                "superclass": super_class.map(|super_class| self.visit_expr(ast, super_class)),
+                "methods": self.stmts(ast, methods),
+                "doc": doc,
+            }),
+            Stmt::Expression { expression, .. } => json!({
+                "kind": "Expression",
+                "span": span,
+                "expression": self.visit_expr(ast, *expression),
+            }),
+            Stmt::Function(Function {
+                name,
+                params,
+                body,
+                doc,
+                ..
+            }) => json!({
+                "kind": "Function",
+                "span": span,
+                "name": token_json(name),
+                "params": params.iter().map(token_json).collect::<Vec<_>>(),
+                "body": self.stmts(ast, body),
+                "doc": doc,
+            }),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => json!({
+                "kind": "If",
+                "span": span,
+                "condition": self.visit_expr(ast, *condition),
+                "then": self.visit_stmt(ast, *then_branch),
+                "else": else_branch.map(|else_branch| self.visit_stmt(ast, else_branch)),
+            }),
+            Stmt::Print { expression, .. } => json!({
+                "kind": "Print",
+                "span": span,
+                "expression": self.visit_expr(ast, *expression),
+            }),
+            Stmt::Return { keyword, value, .. } => json!({
+                "kind": "Return",
+                "span": span,
+                "keyword": token_json(keyword),
+                "value": value.map(|value| self.visit_expr(ast, value)),
+            }),
+            Stmt::Var {
+                name,
+                initializer,
+                doc,
+                ..
+            } => json!({
+                "kind": "Var",
+                "span": span,
+                "name": token_json(name),
+                "initializer": initializer.map(|initializer| self.visit_expr(ast, initializer)),
+                "doc": doc,
+            }),
+            Stmt::While {
+                condition, body, ..
+            } => json!({
+                "kind": "While",
+                "span": span,
+                "condition": self.visit_expr(ast, *condition),
+                "body": self.visit_stmt(ast, *body),
+            }),
+        }
+    }
+}
+
+impl<'t, 'a: 't> ExprVisitor<'t, 'a, Value> for Json {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) -> Value {
+        let span = span_json(ast.expr_span(id));
+        match &ast[id] {
+            Expr::Assign { name, value } => json!({
+                "kind": "Assign",
+                "span": span,
+                "name": token_json(name),
+                "value": self.visit_expr(ast, *value),
+            }),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => json!({
+                "kind": "Binary",
+                "span": span,
+                "left": self.visit_expr(ast, *left),
+                "operator": token_json(operator),
+                "right": self.visit_expr(ast, *right),
+            }),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => json!({
+                "kind": "Call",
+                "span": span,
+                "callee": self.visit_expr(ast, *callee),
+                "paren": token_json(paren),
+                "arguments": self.exprs(ast, arguments),
+            }),
+            Expr::Get { object, name } => json!({
+                "kind": "Get",
+                "span": span,
+                "object": self.visit_expr(ast, *object),
+                "name": token_json(name),
+            }),
+            Expr::Grouping { expression, .. } => json!({
+                "kind": "Grouping",
+                "span": span,
+                "expression": self.visit_expr(ast, *expression),
+            }),
+            Expr::Interpolation { parts, .. } => json!({
+                "kind": "Interpolation",
+                "span": span,
+                "parts": self.exprs(ast, parts),
+            }),
+            Expr::Literal { value, .. } => {
+                let (typ, value) = match value {
+                    Literal::String(s) => ("string", json!(s)),
+                    // JSON has no infinity, which a literal too large for an f64 rounds to
+                    Literal::Number(n) if !n.is_finite() => ("number", json!(n.to_string())),
+                    Literal::Number(n) => ("number", json!(n)),
+                    Literal::True => ("boolean", json!(true)),
+                    Literal::False => ("boolean", json!(false)),
+                    Literal::Nil => ("nil", Value::Null),
+                };
+                json!({
+                    "kind": "Literal",
+                    "span": span,
+                    "type": typ,
+                    "value": value,
+                })
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => json!({
+                "kind": "Logical",
+                "span": span,
+                "left": self.visit_expr(ast, *left),
+                "operator": token_json(operator),
+                "right": self.visit_expr(ast, *right),
+            }),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => json!({
+                "kind": "Set",
+                "span": span,
+                "object": self.visit_expr(ast, *object),
+                "name": token_json(name),
+                "value": self.visit_expr(ast, *value),
+            }),
+            Expr::Super { keyword, method } => json!({
+                "kind": "Super",
+                "span": span,
+                "keyword": token_json(keyword),
+                "method": token_json(method),
+            }),
+            Expr::This { keyword } => json!({
+                "kind": "This",
+                "span": span,
+                "keyword": token_json(keyword),
+            }),
+            Expr::Unary { operator, right } => json!({
+                "kind": "Unary",
+                "span": span,
+                "operator": token_json(operator),
+                "right": self.visit_expr(ast, *right),
+            }),
+            Expr::Variable { name } => json!({
+                "kind": "Variable",
+                "span": span,
+                "name": token_json(name),
+            }),
+        }
+    }
+}
+
+fn token_json(token: &Token) -> Value {
+    json!({
+        "lexeme": token.lexeme,
+        "span": span_json(token.span),
+    })
+}
+
+fn span_json(span: Span) -> Value {
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": span.line,
+        "column": span.column,
+    })
+}
@@ -0,0 +1,72 @@
+use crate::interpreter::{InterpreterResult, RuntimeError, Value};
+use crate::token::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Default)]
+pub struct Environment<'a> {
+    values: HashMap<String, Value<'a>>,
+    enclosing: Option<Rc<RefCell<Environment<'a>>>>,
+}
+
+impl<'a> Environment<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment<'a>>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value<'a>) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    // looks `name` up exactly `distance` scopes out from this one
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<Value<'a>> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+        self.enclosing.as_ref()?.borrow().get_at(distance - 1, name)
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &str, value: Value<'a>) {
+        if distance == 0 {
+            self.values.insert(name.to_string(), value);
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow_mut().assign_at(distance - 1, name, value);
+        }
+    }
+
+    pub fn get(&self, name: &Token) -> InterpreterResult<Value<'a>> {
+        self.lookup(name.name())
+            .ok_or_else(|| undefined_variable(name))
+    }
+
+    // walks the chain outwards to the nearest scope that defines `name`
+    pub fn lookup(&self, name: &str) -> Option<Value<'a>> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().lookup(name),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value<'a>) -> InterpreterResult<()> {
+        if let Some(slot) = self.values.get_mut(name.name()) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> RuntimeError {
+    RuntimeError::new(format!("Undefined variable '{}'", name.name()), name.span)
+}
@@ -1,107 +1,251 @@
+use crate::ast::{Ast, Displayed, ExprId};
+use crate::span::Span;
 use crate::token::Token;
 use crate::token_type::Literal;
+use crate::visit::ExprVisitor;
 
 #[derive(Debug, Clone)]
 pub enum Expr<'a> {
     Assign {
-        name: &'a Token<'a>,
-        value: Box<Expr<'a>>,
+        name: Token<'a>,
+        value: ExprId,
     },
     Binary {
-        left: Box<Expr<'a>>,
-        operator: &'a Token<'a>,
-        right: Box<Expr<'a>>,
+        left: ExprId,
+        operator: Token<'a>,
+        right: ExprId,
     },
     Call {
-        callee: Box<Expr<'a>>,
-        paren: &'a Token<'a>,
-        arguments: Vec<Expr<'a>>,
+        callee: ExprId,
+        paren: Token<'a>,
+        arguments: Vec<ExprId>,
     },
     Get {
-        object: Box<Expr<'a>>,
-        name: &'a Token<'a>,
+        object: ExprId,
+        name: Token<'a>,
     },
     Grouping {
-        expression: Box<Expr<'a>>,
+        expression: ExprId,
+        span: Span,
+    },
+    // the string segments of a "${...}" template as literals, interleaved with the embedded
+    // expressions
+    Interpolation {
+        parts: Vec<ExprId>,
+        span: Span,
     },
     Literal {
         value: Literal<'a>,
+        span: Span,
     },
     Logical {
-        left: Box<Expr<'a>>,
-        operator: &'a Token<'a>,
-        right: Box<Expr<'a>>,
+        left: ExprId,
+        operator: Token<'a>,
+        right: ExprId,
     },
     Set {
-        object: Box<Expr<'a>>,
-        name: &'a Token<'a>,
-        value: Box<Expr<'a>>,
+        object: ExprId,
+        name: Token<'a>,
+        value: ExprId,
     },
     Super {
-        keyword: &'a Token<'a>,
-        method: &'a Token<'a>,
+        keyword: Token<'a>,
+        method: Token<'a>,
     },
     This {
-        keyword: &'a Token<'a>,
+        keyword: Token<'a>,
     },
     Unary {
-        operator: &'a Token<'a>,
-        right: Box<Expr<'a>>,
+        operator: Token<'a>,
+        right: ExprId,
     },
     Variable {
-        name: &'a Token<'a>,
+        name: Token<'a>,
     },
 }
 
-impl<'a> std::fmt::Display for Expr<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<'a> Expr<'a> {
+    pub fn span(&self, ast: &Ast) -> Span {
+        match self {
+            Expr::Assign { name, value } => name.span.to(ast.expr_span(*value)),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                ast.expr_span(*left).to(ast.expr_span(*right))
+            }
+            Expr::Call { callee, paren, .. } => ast.expr_span(*callee).to(paren.span),
+            Expr::Get { object, name } => ast.expr_span(*object).to(name.span),
+            Expr::Grouping { span, .. }
+            | Expr::Interpolation { span, .. }
+            | Expr::Literal { span, .. } => *span,
+            Expr::Set { object, value, .. } => ast.expr_span(*object).to(ast.expr_span(*value)),
+            Expr::Super { keyword, method } => keyword.span.to(method.span),
+            Expr::This { keyword } => keyword.span,
+            Expr::Unary { operator, right } => operator.span.to(ast.expr_span(*right)),
+            Expr::Variable { name } => name.span,
+        }
+    }
+
+    // a copy of the node that no longer borrows the source
+    pub fn into_owned(self) -> Expr<'static> {
         match self {
-            Expr::Assign { name, value } => write!(f, "{} = {}", name.lexeme, value),
+            Expr::Assign { name, value } => Expr::Assign {
+                name: name.into_owned(),
+                value,
+            },
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => write!(f, "({} {} {})", left, operator.lexeme, right),
+            } => Expr::Binary {
+                left,
+                operator: operator.into_owned(),
+                right,
+            },
             Expr::Call {
                 callee,
-                paren: _,
+                paren,
                 arguments,
-            } => {
-                write!(f, "{}(", callee)?;
-                for arg in arguments {
-                    write!(f, " {}", arg)?;
-                }
-                write!(f, ")")?;
-                Ok(())
-            }
-            Expr::Get { object, name } => write!(f, "({}).{}", object, name),
-            Expr::Grouping { expression } => write!(f, "({})", expression),
-            Expr::Literal { value } => write!(f, "{}", value),
+            } => Expr::Call {
+                callee,
+                paren: paren.into_owned(),
+                arguments,
+            },
+            Expr::Get { object, name } => Expr::Get {
+                object,
+                name: name.into_owned(),
+            },
+            Expr::Grouping { expression, span } => Expr::Grouping { expression, span },
+            Expr::Interpolation { parts, span } => Expr::Interpolation { parts, span },
+            Expr::Literal { value, span } => Expr::Literal {
+                value: value.into_owned(),
+                span,
+            },
             Expr::Logical {
                 left,
                 operator,
                 right,
-            } => write!(f, "({} {} {})", left, operator.lexeme, right),
+            } => Expr::Logical {
+                left,
+                operator: operator.into_owned(),
+                right,
+            },
             Expr::Set {
                 object,
                 name,
                 value,
-            } => write!(f, "({}).{} = {}", object, name.lexeme, value),
-            Expr::Super { keyword: _, method } => write!(f, "super.{}", method.lexeme),
-            Expr::This { keyword: _ } => write!(f, "this"),
-            Expr::Unary { operator, right } => write!(f, "({}{})", operator.lexeme, right),
-            Expr::Variable { name } => write!(f, "{}", name),
+            } => Expr::Set {
+                object,
+                name: name.into_owned(),
+                value,
+            },
+            Expr::Super { keyword, method } => Expr::Super {
+                keyword: keyword.into_owned(),
+                method: method.into_owned(),
+            },
+            Expr::This { keyword } => Expr::This {
+                keyword: keyword.into_owned(),
+            },
+            Expr::Unary { operator, right } => Expr::Unary {
+                operator: operator.into_owned(),
+                right,
+            },
+            Expr::Variable { name } => Expr::Variable {
+                name: name.into_owned(),
+            },
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Variable<'a> {
-    pub name: &'a Token<'a>,
+impl<'t, 'a> std::fmt::Display for Displayed<'t, 'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        Writer { f }.visit_expr(self.ast, self.id)
+    }
 }
 
-impl<'a> std::fmt::Display for Variable<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.name.lexeme)
+// writes expressions out for `Displayed`
+struct Writer<'f, 'g> {
+    f: &'f mut std::fmt::Formatter<'g>,
+}
+
+impl<'t, 'a: 't> ExprVisitor<'t, 'a, std::fmt::Result> for Writer<'_, '_> {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) -> std::fmt::Result {
+        match &ast[id] {
+            Expr::Assign { name, value } => {
+                write!(self.f, "{} = ", name.lexeme)?;
+                self.visit_expr(ast, *value)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                write!(self.f, "(")?;
+                self.visit_expr(ast, *left)?;
+                write!(self.f, " {} ", operator.lexeme)?;
+                self.visit_expr(ast, *right)?;
+                write!(self.f, ")")
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.visit_expr(ast, *callee)?;
+                write!(self.f, "(")?;
+                for arg in arguments {
+                    write!(self.f, " ")?;
+                    self.visit_expr(ast, *arg)?;
+                }
+                write!(self.f, ")")
+            }
+            Expr::Get { object, name } => {
+                write!(self.f, "(")?;
+                self.visit_expr(ast, *object)?;
+                write!(self.f, ").{}", name.lexeme)
+            }
+            Expr::Grouping { expression, .. } => {
+                write!(self.f, "(")?;
+                self.visit_expr(ast, *expression)?;
+                write!(self.f, ")")
+            }
+            Expr::Interpolation { parts, .. } => {
+                write!(self.f, "\"")?;
+                for part in parts {
+                    match &ast[*part] {
+                        Expr::Literal {
+                            value: Literal::String(s),
+                            ..
+                        } => write!(self.f, "{}", s)?,
+                        _ => {
+                            write!(self.f, "${{")?;
+                            self.visit_expr(ast, *part)?;
+                            write!(self.f, "}}")?;
+                        }
+                    }
+                }
+                write!(self.f, "\"")
+            }
+            Expr::Literal { value, .. } => write!(self.f, "{}", value),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                write!(self.f, "(")?;
+                self.visit_expr(ast, *object)?;
+                write!(self.f, ").{} = ", name.lexeme)?;
+                self.visit_expr(ast, *value)
+            }
+            Expr::Super { method, .. } => write!(self.f, "super.{}", method.lexeme),
+            Expr::This { .. } => write!(self.f, "this"),
+            Expr::Unary { operator, right } => {
+                write!(self.f, "({}", operator.lexeme)?;
+                self.visit_expr(ast, *right)?;
+                write!(self.f, ")")
+            }
+            Expr::Variable { name } => write!(self.f, "{}", name.lexeme),
+        }
     }
 }
@@ -0,0 +1,1015 @@
+use crate::ast::{Ast, ExprId, StmtId};
+use crate::diagnostics::Diagnostic;
+use crate::expr::Expr;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::span::Span;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::token_type::{Literal, TokenType};
+use crate::trivia::TriviaKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BraceStyle {
+    // `if (x) {`
+    SameLine,
+    // `if (x)` with the `{` on a line of its own
+    NextLine,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatConfig {
+    indent_width: usize,
+    max_width: usize,
+    brace_style: BraceStyle,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_width: 100,
+            brace_style: BraceStyle::SameLine,
+        }
+    }
+}
+
+impl FormatConfig {
+    pub fn with_indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+
+    // lines longer than this have their call arguments, parameters and chains of operators
+    // split one per line, where there are any to split
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    pub fn with_brace_style(mut self, brace_style: BraceStyle) -> Self {
+        self.brace_style = brace_style;
+        self
+    }
+}
+
+// formats a whole program, or reports why it can't: only source that scans and parses
+// cleanly is formatted
+pub fn format_source(source: &str, config: &FormatConfig) -> Result<String, Vec<Diagnostic>> {
+    let tokens = Scanner::new(source)
+        .with_trivia()
+        .scan_tokens()
+        .map_err(|errors| errors.iter().map(|e| e.to_diagnostic()).collect::<Vec<_>>())?;
+    let ast = Parser::new(tokens.iter().cloned())
+        .parse_program()
+        .map_err(|errors| errors.iter().map(|e| e.to_diagnostic()).collect::<Vec<_>>())?;
+    Ok(Formatter::new(&ast, &tokens, config).format())
+}
+
+// something between tokens the formatter has to carry over, in source order
+enum Event<'t> {
+    // an `inline` comment sits between the parts of a list or a chain of operators, and is
+    // printed along with them rather than between statements
+    Comment {
+        text: &'t str,
+        trailing: bool,
+        inline: bool,
+    },
+    BlankLine,
+}
+
+// prints a tree back as Lox source. Comments and blank lines come from the trivia of the
+// tokens the tree was parsed from, so those should come from a lossless scan. A comment
+// inside an argument or parameter list or a chain of operators stays next to the part it
+// followed, and one after the header of a statement stays on the header's line. Any other
+// comment is put back before the statement or `}` that follows it, and one that trailed
+// code on its line is appended to the last line printed before that point.
+pub struct Formatter<'t, 'a> {
+    ast: &'t Ast<'a>,
+    tokens: &'t [Token<'a>],
+    config: &'t FormatConfig,
+    events: Vec<(usize, Event<'t>)>,
+    // how many of the events have been printed
+    printed: usize,
+    lines: Vec<String>,
+}
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    pub fn new(ast: &'t Ast<'a>, tokens: &'t [Token<'a>], config: &'t FormatConfig) -> Self {
+        let mut events = Vec::new();
+        for token in tokens {
+            // a newline leading a token ends a blank line, unless it ends a comment's line
+            let mut after_comment = false;
+            for trivia in token.leading() {
+                match trivia.kind {
+                    TriviaKind::LineComment | TriviaKind::BlockComment | TriviaKind::DocComment => {
+                        let text = trivia.text.trim_end();
+                        events.push((
+                            trivia.span.start,
+                            Event::Comment {
+                                text,
+                                trailing: false,
+                                inline: false,
+                            },
+                        ));
+                        after_comment = true;
+                    }
+                    TriviaKind::Newline if after_comment => after_comment = false,
+                    TriviaKind::Newline => events.push((trivia.span.start, Event::BlankLine)),
+                    TriviaKind::Whitespace | TriviaKind::Skipped => {}
+                }
+            }
+            for trivia in token.trailing() {
+                if let TriviaKind::LineComment | TriviaKind::BlockComment | TriviaKind::DocComment =
+                    trivia.kind
+                {
+                    let text = trivia.text.trim_end();
+                    events.push((
+                        trivia.span.start,
+                        Event::Comment {
+                            text,
+                            trailing: true,
+                            inline: false,
+                        },
+                    ));
+                }
+            }
+        }
+        let mut formatter = Self {
+            ast,
+            tokens,
+            config,
+            events,
+            printed: 0,
+            lines: Vec::new(),
+        };
+        formatter.mark_inline_comments();
+        formatter
+    }
+
+    fn mark_inline_comments(&mut self) {
+        let ast = self.ast;
+        let mut gaps = Vec::new();
+        for (_, expr) in ast.exprs() {
+            match expr {
+                Expr::Call {
+                    callee,
+                    paren,
+                    arguments,
+                } => {
+                    let spans = arguments
+                        .iter()
+                        .map(|&argument| ast.expr_span(argument))
+                        .collect::<Vec<_>>();
+                    let open = ast.expr_span(*callee).end;
+                    gaps.extend(list_gaps(open, &spans, paren.span.start));
+                }
+                Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                    gaps.push((ast.expr_span(*left).end, ast.expr_span(*right).start));
+                }
+                _ => {}
+            }
+        }
+        for (_, stmt) in ast.stmts() {
+            if let Stmt::Function(function) = stmt {
+                let spans = function
+                    .params
+                    .iter()
+                    .map(|param| param.span)
+                    .collect::<Vec<_>>();
+                let close = self.params_end(function.params.last());
+                gaps.extend(list_gaps(function.name.span.end, &spans, close));
+            }
+        }
+        for (from, to) in gaps {
+            let first = self.events.partition_point(|(start, _)| *start < from);
+            for (start, event) in &mut self.events[first..] {
+                if *start >= to {
+                    break;
+                }
+                if let Event::Comment { inline, .. } = event {
+                    *inline = true;
+                }
+            }
+        }
+    }
+
+    pub fn format(mut self) -> String {
+        let ast = self.ast;
+        for &statement in ast.statements() {
+            self.stmt(statement, 0);
+        }
+        self.flush(usize::MAX, 0);
+        self.trim_blank_lines();
+        let mut output = self.lines.join("\n");
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output
+    }
+
+    fn stmt(&mut self, id: StmtId, indent: usize) {
+        let ast = self.ast;
+        self.flush(ast.stmt_span(id).start, indent);
+        if let Some(for_loop) = self.for_loop(id) {
+            return self.for_stmt(for_loop, indent);
+        }
+        match &ast[id] {
+            Stmt::Block { statements, span } => {
+                self.braced(String::new(), statements, span.start, indent)
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                ..
+            } => {
+                let mut header = format!("class {}", name.lexeme);
+                if let Some(super_class) = *super_class {
+                    header.push_str(" < ");
+                    header.push_str(&self.flat(super_class));
+                }
+                self.braced(header, methods, name.span.start, indent)
+            }
+            Stmt::Expression { expression, .. } => {
+                let text = self.expr(*expression, indent, 1);
+                self.push(indent, format!("{};", text));
+            }
+            Stmt::Function(function) => {
+                let keyword = match self.token_at(function.span.start) {
+                    Some(token) if token.typ == TokenType::FUN => "fun ",
+                    // methods are declared without `fun`
+                    _ => "",
+                };
+                let texts = function
+                    .params
+                    .iter()
+                    .map(|param| param.lexeme.to_string())
+                    .collect();
+                let spans = function
+                    .params
+                    .iter()
+                    .map(|param| param.span)
+                    .collect::<Vec<_>>();
+                let close = self.params_end(function.params.last());
+                let params = self.items(texts, &spans, function.name.span.end, close);
+                let callee = format!("{}{}", keyword, function.name.lexeme);
+                // leave room for the ` {` that follows the parameters
+                let header = self.list(callee, params, indent, 2);
+                self.braced(header, &function.body, function.name.span.start, indent)
+            }
+            Stmt::If { .. } => self.if_stmt(id, indent, String::new()),
+            Stmt::Print { expression, .. } => {
+                let text = self.expr(*expression, indent, "print ;".len());
+                self.push(indent, format!("print {};", text));
+            }
+            Stmt::Return { value, .. } => match *value {
+                Some(value) => {
+                    let text = self.expr(value, indent, "return ;".len());
+                    self.push(indent, format!("return {};", text));
+                }
+                None => self.push(indent, "return;".to_string()),
+            },
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                let text = self.var(name, *initializer, indent);
+                self.push(indent, text);
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                let header = format!(
+                    "while ({})",
+                    self.expr(*condition, indent, "while ()".len())
+                );
+                self.body(header, *body, indent);
+            }
+        }
+    }
+
+    fn var(&self, name: &Token<'a>, initializer: Option<ExprId>, indent: usize) -> String {
+        match initializer {
+            Some(initializer) => {
+                let used = "var  = ;".len() + width(&name.lexeme);
+                format!(
+                    "var {} = {};",
+                    name.lexeme,
+                    self.expr(initializer, indent, used)
+                )
+            }
+            None => format!("var {};", name.lexeme),
+        }
+    }
+
+    // `lead` is what goes before `if` on its first line, such as the `} else ` of a chain
+    fn if_stmt(&mut self, id: StmtId, indent: usize, lead: String) {
+        let ast = self.ast;
+        let Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } = &ast[id]
+        else {
+            return;
+        };
+        let used = width(&lead) + "if ()".len();
+        let header = format!("{}if ({})", lead, self.expr(*condition, indent, used));
+        self.body(header, *then_branch, indent);
+
+        let Some(else_branch) = *else_branch else {
+            return;
+        };
+        // `else` joins the `}` of a block, and starts a line of its own otherwise
+        let closing = format!("{}}}", self.indentation(indent));
+        let lead = if self.config.brace_style == BraceStyle::SameLine
+            && self.lines.last() == Some(&closing)
+        {
+            self.lines.pop();
+            "} else".to_string()
+        } else {
+            "else".to_string()
+        };
+        match &ast[else_branch] {
+            Stmt::If { .. } => self.if_stmt(else_branch, indent, format!("{} ", lead)),
+            _ => self.body(lead, else_branch, indent),
+        }
+    }
+
+    fn for_stmt(&mut self, for_loop: ForLoop, indent: usize) {
+        let ast = self.ast;
+        let mut header = "for (".to_string();
+        match for_loop.initializer.map(|initializer| &ast[initializer]) {
+            Some(Stmt::Var {
+                name, initializer, ..
+            }) => header.push_str(&self.var(name, *initializer, indent)),
+            Some(Stmt::Expression { expression, .. }) => {
+                header.push_str(&self.flat(*expression));
+                header.push(';');
+            }
+            _ => header.push(';'),
+        }
+        if let Some(condition) = for_loop.condition {
+            header.push(' ');
+            header.push_str(&self.flat(condition));
+        }
+        header.push(';');
+        if let Some(increment) = for_loop.increment {
+            header.push(' ');
+            header.push_str(&self.flat(increment));
+        }
+        header.push(')');
+        self.body(header, for_loop.body, indent);
+    }
+
+    // prints the statement a `header` such as `while (x)` governs
+    fn body(&mut self, mut header: String, body: StmtId, indent: usize) {
+        let ast = self.ast;
+        match &ast[body] {
+            Stmt::Block { statements, span } if self.for_loop(body).is_none() => {
+                self.braced(header, statements, span.start, indent)
+            }
+            _ => {
+                let ended = self.header_comments(&mut header, ast.stmt_span(body).start, true);
+                // a single line goes on the header's line when it fits there
+                let lines = self.buffered(|this| this.stmt(body, indent + 1));
+                match lines.as_slice() {
+                    [line]
+                        if !ended
+                            && self.fits(indent, width(&header) + 1 + width(line.trim_start())) =>
+                    {
+                        self.push(indent, format!("{} {}", header, line.trim_start()))
+                    }
+                    _ => {
+                        self.push(indent, header);
+                        self.lines.extend(lines);
+                    }
+                }
+            }
+        }
+    }
+
+    // prints `header { statements }`. `from` is where the search for the source's braces
+    // starts, so that comments before the `}` stay inside.
+    fn braced(&mut self, mut header: String, statements: &[StmtId], from: usize, indent: usize) {
+        if !header.is_empty() {
+            // a comment running to the end of the line can only stay if the `{` goes on the next
+            let line_comments = self.config.brace_style == BraceStyle::NextLine;
+            self.header_comments(&mut header, self.opening_brace(from), line_comments);
+        }
+        match (self.config.brace_style, header.is_empty()) {
+            (_, true) => self.push(indent, "{".to_string()),
+            (BraceStyle::SameLine, false) => self.push(indent, format!("{} {{", header)),
+            (BraceStyle::NextLine, false) => {
+                self.push(indent, header);
+                self.push(indent, "{".to_string());
+            }
+        }
+        let opened = self.lines.len();
+        for &statement in statements {
+            self.stmt(statement, indent + 1);
+        }
+        self.flush(self.closing_brace(from), indent + 1);
+        self.trim_blank_lines();
+        if self.lines.len() == opened {
+            if let Some(line) = self.lines.last_mut() {
+                line.push('}');
+            }
+        } else {
+            self.push(indent, "}".to_string());
+        }
+    }
+
+    // renders an expression, splitting it over lines when it doesn't fit in what is left of
+    // a line that already has `used` columns of other text on it
+    fn expr(&self, id: ExprId, indent: usize, used: usize) -> String {
+        let flat = self.flat(id);
+        // a comment running to the end of its line leaves the flat form split over lines
+        if !flat.contains('\n') && self.fits(indent, used + width(&flat)) {
+            return flat;
+        }
+        match &self.ast[id] {
+            Expr::Call {
+                callee, arguments, ..
+            } if !arguments.is_empty() => {
+                let arguments = arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &argument)| {
+                        let comma = usize::from(i + 1 < arguments.len());
+                        self.expr(argument, indent + 1, comma)
+                    })
+                    .collect::<Vec<_>>();
+                let arguments = self.arguments(id, arguments);
+                self.list(self.flat(*callee), arguments, indent, used)
+            }
+            Expr::Binary { .. } | Expr::Logical { .. } => {
+                // the first operand goes on the line so far, the others on lines of their own
+                let items = self.chain(id, |i, operand, operator| {
+                    if i == 0 {
+                        self.expr(operand, indent, used + 1 + width(operator))
+                    } else {
+                        self.expr(operand, indent + 1, 1 + width(operator))
+                    }
+                });
+                let mut lines = self.item_lines(&items[0], 0);
+                for item in &items[1..] {
+                    lines.extend(self.item_lines(item, indent + 1));
+                }
+                lines.join("\n")
+            }
+            Expr::Assign { name, value } => {
+                let used = used + width(&name.lexeme) + " = ".len();
+                format!("{} = {}", name.lexeme, self.expr(*value, indent, used))
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let target = format!("{}.{} = ", self.flat(*object), name.lexeme);
+                let used = used + width(&target);
+                format!("{}{}", target, self.expr(*value, indent, used))
+            }
+            _ => flat,
+        }
+    }
+
+    // `callee(items)`, or with the items one per line if that doesn't fit
+    fn list(&self, callee: String, items: Vec<Item<'t>>, indent: usize, used: usize) -> String {
+        let flat = format!("{}({})", callee, joined(&items));
+        if items.is_empty() || !flat.contains('\n') && self.fits(indent, used + width(&flat)) {
+            return flat;
+        }
+        let lines = items
+            .iter()
+            .flat_map(|item| self.item_lines(item, indent + 1))
+            .collect::<Vec<_>>();
+        format!(
+            "{}(\n{}\n{})",
+            callee,
+            lines.join("\n"),
+            self.indentation(indent)
+        )
+    }
+
+    // the arguments of a call, already rendered, with the comments between them
+    fn arguments(&self, id: ExprId, texts: Vec<String>) -> Vec<Item<'t>> {
+        let ast = self.ast;
+        let Expr::Call {
+            callee,
+            paren,
+            arguments,
+        } = &ast[id]
+        else {
+            return Vec::new();
+        };
+        let spans = arguments
+            .iter()
+            .map(|&argument| ast.expr_span(argument))
+            .collect::<Vec<_>>();
+        self.items(texts, &spans, ast.expr_span(*callee).end, paren.span.start)
+    }
+
+    // the items of a list between bytes `open` and `close` of the source, followed by commas
+    // but the last. A comment between two items goes with the first if it was on its line,
+    // and with the second otherwise.
+    fn items(
+        &self,
+        texts: Vec<String>,
+        spans: &[Span],
+        open: usize,
+        close: usize,
+    ) -> Vec<Item<'t>> {
+        let count = texts.len();
+        let mut items = texts
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut text)| {
+                if i + 1 < count {
+                    text.push(',');
+                }
+                Item::new(text)
+            })
+            .collect::<Vec<_>>();
+        for (i, span) in spans.iter().enumerate() {
+            let from = if i == 0 { open } else { spans[i - 1].end };
+            for (text, trailing) in self.comments(from, span.start) {
+                match i.checked_sub(1) {
+                    Some(previous) if trailing => items[previous].after.push(text),
+                    _ => items[i].before.push(text),
+                }
+            }
+        }
+        if let (Some(last), Some(span)) = (items.last_mut(), spans.last()) {
+            last.after
+                .extend(self.comments(span.end, close).map(|(text, _)| text));
+        }
+        items
+    }
+
+    // the operands of a chain of operators of one precedence, such as `a - b + c`, rendered
+    // by `render` and each followed by the operator after it. A comment before an operator
+    // stays there unless it runs to the end of its line, and then goes after the operator.
+    fn chain(&self, id: ExprId, render: impl Fn(usize, ExprId, &str) -> String) -> Vec<Item<'t>> {
+        let ast = self.ast;
+        let (operands, operators) = self.operands(id);
+        let mut items = Vec::<Item>::new();
+        for (i, &operand) in operands.iter().enumerate() {
+            let operator = operators.get(i).map_or("", |operator| &*operator.lexeme);
+            let mut item = Item::new(render(i, operand, operator));
+            if let Some(previous) = i.checked_sub(1) {
+                let operator = operators[previous].span;
+                for (text, trailing) in self.comments(operator.end, ast.expr_span(operand).start) {
+                    match items.last_mut() {
+                        Some(last) if trailing => last.after.push(text),
+                        _ => item.before.push(text),
+                    }
+                }
+            }
+            if let Some(operator) = operators.get(i) {
+                for (text, _) in self.comments(ast.expr_span(operand).end, operator.span.start) {
+                    if is_line_comment(text) {
+                        item.after.push(text);
+                    } else {
+                        item.text.push(' ');
+                        item.text.push_str(text);
+                    }
+                }
+                item.text.push(' ');
+                item.text.push_str(&operator.lexeme);
+            }
+            items.push(item);
+        }
+        items
+    }
+
+    fn operands(&self, id: ExprId) -> (Vec<ExprId>, Vec<&'t Token<'a>>) {
+        let ast = self.ast;
+        match &ast[id] {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let (mut operands, mut operators) = match &ast[*left] {
+                    Expr::Binary {
+                        operator: inner, ..
+                    }
+                    | Expr::Logical {
+                        operator: inner, ..
+                    } if precedence(&inner.typ) == precedence(&operator.typ) => {
+                        self.operands(*left)
+                    }
+                    _ => (vec![*left], Vec::new()),
+                };
+                operands.push(*right);
+                operators.push(operator);
+                (operands, operators)
+            }
+            _ => (vec![id], Vec::new()),
+        }
+    }
+
+    // the lines of an item at `indent`: a comment before it that runs to the end of its line
+    // gets a line of its own, any other goes before the item on its line
+    fn item_lines(&self, item: &Item, indent: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut line = self.indentation(indent);
+        for comment in &item.before {
+            line.push_str(comment);
+            if is_line_comment(comment) {
+                lines.push(std::mem::replace(&mut line, self.indentation(indent)));
+            } else {
+                line.push(' ');
+            }
+        }
+        line.push_str(&item.text);
+        for comment in &item.after {
+            line.push(' ');
+            line.push_str(comment);
+        }
+        lines.push(line);
+        lines
+    }
+
+    // an expression on a single line
+    fn flat(&self, id: ExprId) -> String {
+        match &self.ast[id] {
+            Expr::Assign { name, value } => format!("{} = {}", name.lexeme, self.flat(*value)),
+            Expr::Binary { .. } | Expr::Logical { .. } => {
+                joined(&self.chain(id, |_, operand, _| self.flat(operand)))
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|&argument| self.flat(argument))
+                    .collect::<Vec<_>>();
+                let arguments = self.arguments(id, arguments);
+                format!("{}({})", self.flat(*callee), joined(&arguments))
+            }
+            Expr::Get { object, name } => format!("{}.{}", self.flat(*object), name.lexeme),
+            Expr::Grouping { expression, .. } => format!("({})", self.flat(*expression)),
+            Expr::Interpolation { parts, .. } => {
+                let mut text = "\"".to_string();
+                for &part in parts {
+                    match &self.ast[part] {
+                        Expr::Literal {
+                            value: Literal::String(s),
+                            span,
+                        } if self.is_segment(span.start) => text.push_str(&escape(s)),
+                        _ => {
+                            text.push_str("${");
+                            text.push_str(&self.flat(part));
+                            text.push('}');
+                        }
+                    }
+                }
+                text.push('"');
+                text
+            }
+            Expr::Literal { value, span } => match value {
+                Literal::String(s) => format!("\"{}\"", escape(s)),
+                // a number keeps its spelling, which may be hex or too large to print back
+                Literal::Number(_) => match self.token_at(span.start) {
+                    Some(token) => token.lexeme.to_string(),
+                    None => value.to_string(),
+                },
+                _ => value.to_string(),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => format!(
+                "{}.{} = {}",
+                self.flat(*object),
+                name.lexeme,
+                self.flat(*value)
+            ),
+            Expr::Super { method, .. } => format!("super.{}", method.lexeme),
+            Expr::This { .. } => "this".to_string(),
+            Expr::Unary { operator, right } => {
+                format!("{}{}", operator.lexeme, self.flat(*right))
+            }
+            Expr::Variable { name } => name.lexeme.to_string(),
+        }
+    }
+
+    // prints the comments and blank lines that come before byte `position` of the source
+    fn flush(&mut self, position: usize, indent: usize) {
+        while let Some((start, event)) = self.events.get(self.printed) {
+            if *start >= position {
+                break;
+            }
+            match *event {
+                Event::BlankLine => {
+                    // never at the start of the output or of a block, and never twice
+                    if self
+                        .lines
+                        .last()
+                        .is_some_and(|line| !line.is_empty() && !line.ends_with('{'))
+                    {
+                        self.lines.push(String::new());
+                    }
+                }
+                // printed with the parts they sit between
+                Event::Comment { inline: true, .. } => {}
+                Event::Comment { text, trailing, .. } => match self.lines.last_mut() {
+                    Some(line) if trailing && !line.is_empty() => {
+                        line.push(' ');
+                        line.push_str(text);
+                    }
+                    _ => self.push(indent, text.to_string()),
+                },
+            }
+            self.printed += 1;
+        }
+    }
+
+    // moves the comments that followed a statement's header on its line onto the end of
+    // `header`, up to byte `position` where what the header governs starts. One that runs to
+    // the end of the line is only moved if `line_comments` allows, and whether one was is
+    // returned, since nothing more can then go on the line.
+    fn header_comments(
+        &mut self,
+        header: &mut String,
+        position: usize,
+        line_comments: bool,
+    ) -> bool {
+        while let Some((start, event)) = self.events.get(self.printed) {
+            if *start >= position {
+                break;
+            }
+            match *event {
+                // already printed as part of the header
+                Event::Comment { inline: true, .. } => {}
+                Event::Comment {
+                    text,
+                    trailing: true,
+                    ..
+                } if line_comments || !is_line_comment(text) => {
+                    header.push(' ');
+                    header.push_str(text);
+                    if is_line_comment(text) {
+                        self.printed += 1;
+                        return true;
+                    }
+                }
+                _ => break,
+            }
+            self.printed += 1;
+        }
+        false
+    }
+
+    // the comments between bytes `from` and `to` of the source, each with whether it trailed
+    // code on its line
+    fn comments(&self, from: usize, to: usize) -> impl Iterator<Item = (&'t str, bool)> + '_ {
+        let first = self.events.partition_point(|(start, _)| *start < from);
+        self.events[first..]
+            .iter()
+            .take_while(move |(start, _)| *start < to)
+            .filter_map(|(_, event)| match *event {
+                Event::Comment { text, trailing, .. } => Some((text, trailing)),
+                Event::BlankLine => None,
+            })
+    }
+
+    // adds `text` at `indent`; lines after its first carry their own indentation
+    fn push(&mut self, indent: usize, text: String) {
+        let mut lines = text.split('\n');
+        if let Some(first) = lines.next() {
+            self.lines
+                .push(format!("{}{}", self.indentation(indent), first));
+        }
+        self.lines.extend(lines.map(str::to_string));
+    }
+
+    fn buffered(&mut self, f: impl FnOnce(&mut Self)) -> Vec<String> {
+        let outer = std::mem::take(&mut self.lines);
+        f(self);
+        std::mem::replace(&mut self.lines, outer)
+    }
+
+    fn trim_blank_lines(&mut self) {
+        while self.lines.last().is_some_and(|line| line.is_empty()) {
+            self.lines.pop();
+        }
+    }
+
+    fn indentation(&self, indent: usize) -> String {
+        " ".repeat(indent * self.config.indent_width)
+    }
+
+    fn fits(&self, indent: usize, columns: usize) -> bool {
+        indent * self.config.indent_width + columns <= self.config.max_width
+    }
+
+    fn token_index(&self, position: usize) -> Option<usize> {
+        self.tokens
+            .binary_search_by_key(&position, |token| token.span.start)
+            .ok()
+    }
+
+    fn token_at(&self, position: usize) -> Option<&'t Token<'a>> {
+        self.token_index(position).map(|i| &self.tokens[i])
+    }
+
+    // where the first `{` at or after `from` starts
+    fn opening_brace(&self, from: usize) -> usize {
+        let first = self.tokens.partition_point(|token| token.span.start < from);
+        self.tokens[first..]
+            .iter()
+            .find(|token| token.typ == TokenType::LEFT_BRACE)
+            .map_or(usize::MAX, |token| token.span.start)
+    }
+
+    // where the `)` after a function's parameters starts, given the last of them
+    fn params_end(&self, last: Option<&Token>) -> usize {
+        let Some(last) = last else {
+            return usize::MAX;
+        };
+        self.token_index(last.span.start)
+            .and_then(|i| self.tokens.get(i + 1))
+            .map_or(last.span.end, |token| token.span.start)
+    }
+
+    // where the `}` matching the first `{` at or after `from` starts
+    fn closing_brace(&self, from: usize) -> usize {
+        let first = self.tokens.partition_point(|token| token.span.start < from);
+        let mut depth = 0;
+        for token in &self.tokens[first..] {
+            match token.typ {
+                TokenType::LEFT_BRACE => depth += 1,
+                TokenType::RIGHT_BRACE if depth == 1 => return token.span.start,
+                TokenType::RIGHT_BRACE => depth -= 1,
+                _ => {}
+            }
+        }
+        usize::MAX
+    }
+
+    // whether a string literal inside a template is one of its segments rather than a
+    // string embedded in `${...}`
+    fn is_segment(&self, position: usize) -> bool {
+        self.token_at(position)
+            .is_some_and(|token| match token.typ {
+                TokenType::INTERPOLATION(_) => true,
+                TokenType::STRING(_) => token.lexeme.starts_with('}'),
+                _ => false,
+            })
+    }
+
+    // the parser desugars `for` into a `while` inside blocks, all spanning the `for`
+    // statement; this finds the loop's clauses again
+    fn for_loop(&self, id: StmtId) -> Option<ForLoop> {
+        let ast = self.ast;
+        let span = ast.stmt_span(id);
+        if self.token_at(span.start)?.typ != TokenType::FOR {
+            return None;
+        }
+        let (initializer, while_loop) = match &ast[id] {
+            Stmt::Block { statements, .. } => match statements.as_slice() {
+                [initializer, while_loop] => (Some(*initializer), *while_loop),
+                _ => return None,
+            },
+            Stmt::While { .. } => (None, id),
+            _ => return None,
+        };
+        let Stmt::While {
+            condition, body, ..
+        } = &ast[while_loop]
+        else {
+            return None;
+        };
+        // an omitted condition becomes a `true` at the `;` after it
+        let condition = match self.token_at(ast.expr_span(*condition).start) {
+            Some(token) if token.typ == TokenType::SEMICOLON => None,
+            _ => Some(*condition),
+        };
+        let (body, increment) = match &ast[*body] {
+            Stmt::Block {
+                statements,
+                span: block,
+            } if *block == span => match statements.as_slice() {
+                [body, increment] => match &ast[*increment] {
+                    Stmt::Expression { expression, .. } => (*body, Some(*expression)),
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            _ => (*body, None),
+        };
+        Some(ForLoop {
+            initializer,
+            condition,
+            increment,
+            body,
+        })
+    }
+}
+
+// a part of an argument or parameter list or of a chain of operators, with what separates
+// it from the next, and the comments that were next to it in the source
+struct Item<'t> {
+    text: String,
+    // before the item, on its line or lines of their own
+    before: Vec<&'t str>,
+    // after the item and its separator, on the same line
+    after: Vec<&'t str>,
+}
+
+impl Item<'_> {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+}
+
+// the items on one line, which only a comment running to the end of its line can break
+fn joined(items: &[Item]) -> String {
+    let mut text = String::new();
+    for item in items {
+        let before = item.before.iter().copied();
+        let after = item.after.iter().copied();
+        for part in before.chain([item.text.as_str()]).chain(after) {
+            text.push_str(part);
+            text.push(if is_line_comment(part) { '\n' } else { ' ' });
+        }
+    }
+    if text.ends_with(' ') {
+        text.pop();
+    }
+    text
+}
+
+// the gaps between the items of a list running from byte `open` to byte `close`
+fn list_gaps(open: usize, spans: &[Span], close: usize) -> Vec<(usize, usize)> {
+    let (Some(first), Some(last)) = (spans.first(), spans.last()) else {
+        return Vec::new();
+    };
+    let mut gaps = vec![(open, first.start), (last.end, close)];
+    gaps.extend(spans.windows(2).map(|pair| (pair[0].end, pair[1].start)));
+    gaps
+}
+
+fn is_line_comment(text: &str) -> bool {
+    text.starts_with("//")
+}
+
+// how tightly a binary or logical operator binds
+fn precedence(operator: &TokenType) -> u8 {
+    match operator {
+        TokenType::OR => 0,
+        TokenType::AND => 1,
+        TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL => 2,
+        TokenType::GREATER | TokenType::GREATER_EQUAL | TokenType::LESS | TokenType::LESS_EQUAL => {
+            3
+        }
+        TokenType::MINUS | TokenType::PLUS => 4,
+        _ => 5,
+    }
+}
+
+struct ForLoop {
+    initializer: Option<StmtId>,
+    condition: Option<ExprId>,
+    increment: Option<ExprId>,
+    body: StmtId,
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+// the source spelling of a string's contents
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,64 +1,537 @@
+use crate::ast::{Ast, ExprId, SideTable, StmtId};
+use crate::callable::{self, Callable, LoxFunction, NativeFunction};
+use crate::class::{self, LoxClass, LoxInstance};
+use crate::diagnostics::{Diagnostic, Renderer};
+use crate::environment::Environment;
 use crate::expr::Expr;
+use crate::span::Span;
+use crate::stmt::Stmt;
+use crate::token::Token;
 use crate::token_type::{Literal, TokenType};
+use crate::visit::ExprVisitor;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
-#[error("{0}")]
-pub struct RuntimeError(String);
+#[error("{message} at {span}!")]
+pub struct RuntimeError {
+    message: String,
+    span: Span,
+    // the calls the error unwound out of, innermost first
+    calls: Vec<Call>,
+}
+
+// a call to `function` made on `line`
+#[derive(Debug, Clone)]
+struct Call {
+    function: String,
+    line: usize,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            calls: Vec::new(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    // records that the error unwound out of a call to `function` made on `line`
+    pub(crate) fn called(mut self, function: &str, line: usize) -> Self {
+        self.calls.push(Call {
+            function: function.to_string(),
+            line,
+        });
+        self
+    }
+
+    // one `[line N] in f()` per function the error unwound out of, innermost first, and a
+    // last `[line N] in script` for the top level, as the reference implementation prints
+    pub fn stack_trace(&self) -> Vec<String> {
+        let mut line = self.span.line;
+        let mut trace = Vec::new();
+        for call in &self.calls {
+            trace.push(format!("[line {}] in {}()", line, call.function));
+            line = call.line;
+        }
+        trace.push(format!("[line {}] in script", line));
+        trace
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.message.clone())
+            .with_code("E401")
+            .with_primary(self.span, "")
+    }
+}
+
+// how many calls may be in progress before a program is stopped with "Stack overflow".
+// A call whose function nests a few blocks takes about 8 KiB of native stack in a release
+// build and ten times that in a debug build, and deeper nesting takes more, so this default
+// wants about 32 MiB of stack in release and 320 MiB in debug. A host running programs on
+// a smaller stack, such as the 8 MiB of a main thread, should lower it with
+// `Interpreter::with_max_call_depth`.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 4096;
 
 pub type InterpreterResult<T> = Result<T, RuntimeError>;
 
+// non-local exits out of statement execution: runtime errors and `return`
+pub(crate) enum Unwind<'a> {
+    Error(RuntimeError),
+    Return(Value<'a>),
+}
+
+impl<'a> From<RuntimeError> for Unwind<'a> {
+    fn from(e: RuntimeError) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+type ExecResult<'a> = Result<(), Unwind<'a>>;
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Value {
+pub enum Value<'a> {
     String(String),
     Number(f64),
     Bool(bool),
     Nil,
+    Callable(Callable<'a>),
+    Class(Rc<LoxClass<'a>>),
+    Instance(Rc<RefCell<LoxInstance<'a>>>),
 }
 
-impl std::fmt::Display for Value {
+impl<'a> std::fmt::Display for Value<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Value::String(s) => write!(f, "{}", s),
             Value::Number(n) => write!(f, "{}", n),
             Value::Bool(b) => write!(f, "{}", b),
             Value::Nil => write!(f, "nil"),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Class(c) => write!(f, "{}", c),
+            Value::Instance(i) => write!(f, "{}", i.borrow()),
         }
     }
 }
 
-pub struct Interpreter {}
+// a program ready to run: its tree and the scope distances the resolver found for it.
+// Functions declared in it hold on to it for as long as they can be called.
+#[derive(Debug, Default)]
+pub struct Program<'a> {
+    pub ast: Ast<'a>,
+    // how many scopes out each resolved variable, `this` and `super` expression finds its
+    // binding; unresolved names are globals
+    pub locals: SideTable<ExprId, usize>,
+}
+
+impl<'a> Program<'a> {
+    pub fn new(ast: Ast<'a>, locals: SideTable<ExprId, usize>) -> Self {
+        Self { ast, locals }
+    }
+}
+
+pub struct Interpreter<'a> {
+    globals: Rc<RefCell<Environment<'a>>>,
+    environment: Rc<RefCell<Environment<'a>>>,
+    // the program that node ids refer to, which changes while a function from another runs
+    program: Rc<Program<'a>>,
+    // how many calls are in progress, and how many may be
+    call_depth: usize,
+    max_call_depth: usize,
+    // where `print` writes, so that an embedder can capture or stream a program's output
+    output: Box<dyn Write + 'a>,
+    // where `report` renders runtime errors, and the host can report other diagnostics
+    diagnostics: Box<dyn Write + 'a>,
+    renderer: Renderer,
+}
+
+impl<'a> Default for Interpreter<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-impl Interpreter {
+impl<'a> Interpreter<'a> {
     pub fn new() -> Self {
-        Self {}
+        let mut globals = Environment::new();
+        globals.define(
+            "clock",
+            Value::Callable(Callable::Native(Rc::new(NativeFunction {
+                arity: 0,
+                function: callable::clock,
+            }))),
+        );
+        let globals = Rc::new(RefCell::new(globals));
+        Self {
+            globals: globals.clone(),
+            environment: globals,
+            program: Rc::new(Program::default()),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            output: Box::new(std::io::stdout()),
+            diagnostics: Box::new(std::io::stderr()),
+            renderer: Renderer::plain(),
+        }
+    }
+
+    pub fn with_output(mut self, output: Box<dyn Write + 'a>) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_diagnostics(mut self, diagnostics: Box<dyn Write + 'a>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    // see `DEFAULT_MAX_CALL_DEPTH` for how much stack a given depth needs
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    pub fn diagnostics(&mut self) -> &mut dyn Write {
+        &mut self.diagnostics
     }
 
-    pub fn interpret<'a>(&self, expr: &Expr<'a>) -> InterpreterResult<Value> {
-        match expr {
-            Expr::Literal { value } => match value {
+    // renders an error from a program that was scanned from `source` into the diagnostics,
+    // followed by the calls that led to it. A sink that can't take it has nowhere better to
+    // report that to, so the failure is dropped.
+    pub fn report(&mut self, error: &RuntimeError, name: &str, source: &str) {
+        let rendered = self.renderer.render(&error.to_diagnostic(), name, source);
+        let _ = write!(self.diagnostics, "{}", rendered);
+        for frame in error.stack_trace() {
+            let _ = writeln!(self.diagnostics, "{}", frame);
+        }
+    }
+
+    pub fn interpret(&mut self, program: Rc<Program<'a>>) -> InterpreterResult<()> {
+        let previous = std::mem::replace(&mut self.program, program.clone());
+        let result = program
+            .ast
+            .statements()
+            .iter()
+            .try_for_each(|&statement| self.execute_stmt(statement));
+        self.program = previous;
+        match result {
+            // a top-level `return` stops the program where it is
+            Ok(()) | Err(Unwind::Return(_)) => Ok(()),
+            Err(Unwind::Error(e)) => Err(e),
+        }
+    }
+
+    // evaluates one expression of `program`, as the REPL does with a bare expression
+    pub fn evaluate(
+        &mut self,
+        program: Rc<Program<'a>>,
+        expr: ExprId,
+    ) -> InterpreterResult<Value<'a>> {
+        let previous = std::mem::replace(&mut self.program, program);
+        let result = self.evaluate_expr(expr);
+        self.program = previous;
+        result
+    }
+
+    fn execute_stmt(&mut self, id: StmtId) -> ExecResult<'a> {
+        let program = self.program.clone();
+        match &program.ast[id] {
+            Stmt::Block { statements, .. } => {
+                let environment = Environment::with_enclosing(self.environment.clone());
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                ..
+            } => {
+                let superclass = match super_class {
+                    Some(super_class) => match self.evaluate_expr(*super_class)? {
+                        Value::Class(superclass) => Some(superclass),
+                        _ => {
+                            return Err(RuntimeError::new(
+                                "Superclass must be a class",
+                                program.ast.expr_span(*super_class),
+                            )
+                            .into())
+                        }
+                    },
+                    None => None,
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.name(), Value::Nil);
+
+                // methods of a subclass close over a scope that binds `super`
+                let mut closure = self.environment.clone();
+                if let Some(superclass) = &superclass {
+                    let mut environment = Environment::with_enclosing(closure);
+                    environment.define("super", Value::Class(superclass.clone()));
+                    closure = Rc::new(RefCell::new(environment));
+                }
+
+                let methods = methods
+                    .iter()
+                    .filter_map(|&method| {
+                        let name = program.ast.function(method)?.name.name();
+                        let function = LoxFunction::new(
+                            program.clone(),
+                            method,
+                            closure.clone(),
+                            name == "init",
+                        );
+                        Some((name.to_string(), Rc::new(function)))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                let class = LoxClass::new(name.name().to_string(), superclass, methods);
+                self.environment
+                    .borrow_mut()
+                    .assign(name, Value::Class(Rc::new(class)))?;
+                Ok(())
+            }
+            Stmt::Expression { expression, .. } => {
+                self.evaluate_expr(*expression)?;
+                Ok(())
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                if is_truthy(&self.evaluate_expr(*condition)?) {
+                    self.execute_stmt(*then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.execute_stmt(*else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::Function(function) => {
+                let name = function.name.name();
+                let function =
+                    LoxFunction::new(program.clone(), id, self.environment.clone(), false);
+                self.environment
+                    .borrow_mut()
+                    .define(name, Value::Callable(Callable::Function(Rc::new(function))));
+                Ok(())
+            }
+            Stmt::Print { expression, span } => {
+                let value = self.evaluate_expr(*expression)?;
+                writeln!(self.output, "{}", value).map_err(|e| {
+                    RuntimeError::new(format!("Could not write output: {}", e), *span)
+                })?;
+                Ok(())
+            }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate_expr(*value)?,
+                    None => Value::Nil,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate_expr(*initializer)?,
+                    None => Value::Nil,
+                };
+                self.environment.borrow_mut().define(name.name(), value);
+                Ok(())
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                while is_truthy(&self.evaluate_expr(*condition)?) {
+                    self.execute_stmt(*body)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &[StmtId],
+        environment: Rc<RefCell<Environment<'a>>>,
+    ) -> ExecResult<'a> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements.iter().try_for_each(|&s| self.execute_stmt(s));
+        self.environment = previous;
+        result
+    }
+
+    // runs the body of a function declared in `program`
+    pub(crate) fn execute_body(
+        &mut self,
+        program: &Rc<Program<'a>>,
+        body: &[StmtId],
+        environment: Rc<RefCell<Environment<'a>>>,
+    ) -> ExecResult<'a> {
+        let previous = std::mem::replace(&mut self.program, program.clone());
+        let result = self.execute_block(body, environment);
+        self.program = previous;
+        result
+    }
+
+    fn look_up_variable(&self, expr: ExprId, name: &Token<'a>) -> InterpreterResult<Value<'a>> {
+        match self.program.locals.get(expr) {
+            Some(&distance) => self
+                .environment
+                .borrow()
+                .get_at(distance, name.name())
+                .ok_or_else(|| {
+                    RuntimeError::new(format!("Undefined variable '{}'", name.name()), name.span)
+                }),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    // evaluates an expression of the program currently running
+    fn evaluate_expr(&mut self, id: ExprId) -> InterpreterResult<Value<'a>> {
+        let program = self.program.clone();
+        self.visit_expr(&program.ast, id)
+    }
+
+    // calls a function or class, refusing once `max_call_depth` calls are in progress so that
+    // runaway recursion is a runtime error rather than an overflow of the host's stack
+    fn call_value(
+        &mut self,
+        callee: Value<'a>,
+        paren: &Token<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> InterpreterResult<Value<'a>> {
+        if self.call_depth == self.max_call_depth {
+            return Err(RuntimeError::new("Stack overflow", paren.span));
+        }
+        self.call_depth += 1;
+        let result = self.call_unchecked(callee, paren, arguments);
+        self.call_depth -= 1;
+        result
+    }
+
+    fn call_unchecked(
+        &mut self,
+        callee: Value<'a>,
+        paren: &Token<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> InterpreterResult<Value<'a>> {
+        match callee {
+            Value::Callable(callable) => {
+                if arguments.len() != callable.arity() {
+                    return Err(RuntimeError::new(
+                        format!(
+                            "Expected {} arguments but got {}",
+                            callable.arity(),
+                            arguments.len()
+                        ),
+                        paren.span,
+                    ));
+                }
+                let result = callable.call(self, arguments);
+                match &callable {
+                    Callable::Function(function) => {
+                        result.map_err(|e| e.called(function.name(), paren.span.line))
+                    }
+                    Callable::Native(_) => result,
+                }
+            }
+            Value::Class(class) => {
+                if arguments.len() != class.arity() {
+                    return Err(RuntimeError::new(
+                        format!(
+                            "Expected {} arguments but got {}",
+                            class.arity(),
+                            arguments.len()
+                        ),
+                        paren.span,
+                    ));
+                }
+                let instance = Rc::new(RefCell::new(LoxInstance::new(class.clone())));
+                if let Some(initializer) = class.find_method("init") {
+                    initializer
+                        .bind(Value::Instance(instance.clone()))
+                        .call(self, arguments)
+                        .map_err(|e| e.called("init", paren.span.line))?;
+                }
+                Ok(Value::Instance(instance))
+            }
+            _ => Err(RuntimeError::new(
+                "Can only call functions and classes",
+                paren.span,
+            )),
+        }
+    }
+}
+
+// evaluates an expression of `ast`, which must be the tree of the program currently running
+// so that the ids in `locals` refer to its nodes
+impl<'t, 'a: 't> ExprVisitor<'t, 'a, InterpreterResult<Value<'a>>> for Interpreter<'a> {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) -> InterpreterResult<Value<'a>> {
+        match &ast[id] {
+            Expr::Literal { value, .. } => match value {
                 Literal::String(s) => Ok(Value::String(s.to_string())),
                 Literal::Number(n) => Ok(Value::Number(*n)),
                 Literal::True => Ok(Value::Bool(true)),
                 Literal::False => Ok(Value::Bool(false)),
                 Literal::Nil => Ok(Value::Nil),
             },
-            Expr::Grouping { expression } => self.interpret(expression),
+            Expr::Grouping { expression, .. } => self.visit_expr(ast, *expression),
+            Expr::Interpolation { parts, .. } => {
+                let mut s = String::new();
+                for part in parts {
+                    s.push_str(&self.visit_expr(ast, *part)?.to_string());
+                }
+                Ok(Value::String(s))
+            }
             Expr::Unary { operator, right } => {
-                let right = self.interpret(right)?;
+                let right = self.visit_expr(ast, *right)?;
                 match operator.typ {
                     TokenType::MINUS => match right {
                         Value::Number(n) => Ok(Value::Number(-n)),
-                        _ => Err(RuntimeError("Operand must be a number".to_string())),
-                    },
-                    TokenType::BANG => match right {
-                        Value::Bool(b) => Ok(Value::Bool(!b)),
-                        _ => Err(RuntimeError("Operand must be a boolean".to_string())),
+                        _ => Err(RuntimeError::new("Operand must be a number", operator.span)),
                     },
-                    _ => Err(RuntimeError(format!(
-                        "Unknown unary operator: {}",
-                        operator
-                    ))),
+                    TokenType::BANG => Ok(Value::Bool(!is_truthy(&right))),
+                    _ => Err(RuntimeError::new(
+                        format!("Unknown unary operator: {}", operator.lexeme),
+                        operator.span,
+                    )),
+                }
+            }
+            // yields whichever operand decided the result, not a boolean
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.visit_expr(ast, *left)?;
+                let decided = match operator.typ {
+                    TokenType::OR => is_truthy(&left),
+                    _ => !is_truthy(&left),
+                };
+                if decided {
+                    Ok(left)
+                } else {
+                    self.visit_expr(ast, *right)
                 }
             }
             Expr::Binary {
@@ -66,55 +539,133 @@ impl Interpreter {
                 operator,
                 right,
             } => {
-                let left = self.interpret(left)?;
-                let right = self.interpret(right)?;
+                let left = self.visit_expr(ast, *left)?;
+                let right = self.visit_expr(ast, *right)?;
                 match operator.typ {
                     TokenType::PLUS => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
                         (Value::String(l), Value::String(r)) => {
                             Ok(Value::String(format!("{}{}", l, r)))
                         }
-                        _ => Err(RuntimeError(
-                            "Operands must be two numbers or two strings".to_string(),
+                        _ => Err(RuntimeError::new(
+                            "Operands must be two numbers or two strings",
+                            operator.span,
                         )),
                     },
                     TokenType::MINUS => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::STAR => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::SLASH => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::GREATER => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l > r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::GREATER_EQUAL => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l >= r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::LESS => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l < r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
                     TokenType::LESS_EQUAL => match (left, right) {
                         (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l <= r)),
-                        _ => Err(RuntimeError("Operands must be numbers".to_string())),
+                        _ => Err(RuntimeError::new("Operands must be numbers", operator.span)),
                     },
+                    // values of different types are never equal, and classes, instances and
+                    // functions are only equal to themselves
                     TokenType::BANG_EQUAL => Ok(Value::Bool(left != right)),
                     TokenType::EQUAL_EQUAL => Ok(Value::Bool(left == right)),
-                    _ => Err(RuntimeError(format!(
-                        "Unknown binary operator: {}",
-                        operator
-                    ))),
+                    _ => Err(RuntimeError::new(
+                        format!("Unknown binary operator: {}", operator.lexeme),
+                        operator.span,
+                    )),
+                }
+            }
+            Expr::Variable { name } => self.look_up_variable(id, name),
+            Expr::Assign { name, value } => {
+                let value = self.visit_expr(ast, *value)?;
+                match self.program.locals.get(id) {
+                    Some(&distance) => self.environment.borrow_mut().assign_at(
+                        distance,
+                        name.name(),
+                        value.clone(),
+                    ),
+                    None => self.globals.borrow_mut().assign(name, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let callee = self.visit_expr(ast, *callee)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.visit_expr(ast, *argument))
+                    .collect::<InterpreterResult<Vec<_>>>()?;
+                self.call_value(callee, paren, arguments)
+            }
+            Expr::Get { object, name } => match self.visit_expr(ast, *object)? {
+                Value::Instance(instance) => class::get(&instance, name),
+                _ => Err(RuntimeError::new(
+                    "Only instances have properties",
+                    name.span,
+                )),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => match self.visit_expr(ast, *object)? {
+                Value::Instance(instance) => {
+                    let value = self.visit_expr(ast, *value)?;
+                    instance.borrow_mut().set(name, value.clone());
+                    Ok(value)
+                }
+                _ => Err(RuntimeError::new("Only instances have fields", name.span)),
+            },
+            Expr::This { keyword } => self.look_up_variable(id, keyword),
+            Expr::Super { keyword, method } => {
+                // `this` is bound in the scope just inside the one that binds `super`
+                let (superclass, instance) = match self.program.locals.get(id) {
+                    Some(&distance) => {
+                        let environment = self.environment.borrow();
+                        (
+                            environment.get_at(distance, "super"),
+                            environment.get_at(distance - 1, "this"),
+                        )
+                    }
+                    None => (None, None),
+                };
+                let (Some(Value::Class(superclass)), Some(instance)) = (superclass, instance)
+                else {
+                    return Err(RuntimeError::new("Can't use 'super' here", keyword.span));
+                };
+                match superclass.find_method(method.name()) {
+                    Some(method) => Ok(Value::Callable(Callable::Function(Rc::new(
+                        method.bind(instance),
+                    )))),
+                    None => Err(RuntimeError::new(
+                        format!("Undefined property '{}'", method.name()),
+                        method.span,
+                    )),
                 }
             }
-            _ => Err(RuntimeError(format!("Unknown expression: {}", expr))),
         }
     }
 }
+
+// `nil` and `false` are falsey, everything else is truthy
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Nil | Value::Bool(false))
+}
@@ -1,8 +1,19 @@
+pub mod ast;
+pub mod callable;
+pub mod class;
+pub mod diagnostics;
+pub mod dump;
+pub mod environment;
 pub mod expr;
+pub mod formatter;
 pub mod interpreter;
 pub mod parser;
+pub mod read_scanner;
+pub mod resolver;
 pub mod scanner;
+pub mod span;
 pub mod stmt;
 pub mod token;
 pub mod token_type;
-mod utils;
+pub mod trivia;
+pub mod visit;
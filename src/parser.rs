@@ -1,198 +1,716 @@
+use crate::ast::{Ast, ExprId, StmtId};
+use crate::diagnostics::Diagnostic;
 use crate::expr::Expr;
+use crate::span::Span;
+use crate::stmt::{self, Stmt};
 use crate::token::Token;
 use crate::token_type::{Literal, TokenType};
+use std::borrow::Cow;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ParserError<'a> {
-    #[error("Unexpected Token: Expected {0} on line {}, but got {}!", .1.line, .1.lexeme)]
-    UnexpectedToken(&'static str, &'a Token<'a>),
-    #[error("Unexpected EOF: {0}")]
-    UnexpectedEOF(&'static str),
+    #[error("Unexpected Token: Expected {0} at {}, but got {}!", .1.span, .1.lexeme)]
+    UnexpectedToken(&'static str, Token<'a>),
+    #[error("Unexpected EOF: Expected {0}, but no token left!")]
+    UnexpectedEOF(&'static str, Token<'a>),
+    #[error("Invalid assignment target at {}: {}!", .0.span, .0.lexeme)]
+    InvalidAssignmentTarget(Token<'a>),
+    #[error("Too many {0} at {}: can't have more than 255!", .1.span)]
+    TooMany(&'static str, Token<'a>),
 }
 
-type ParserResult<'a> = Result<Expr<'a>, ParserError<'a>>;
+impl<'a> ParserError<'a> {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            ParserError::UnexpectedToken(expected, token) => {
+                Diagnostic::error(format!("Expected {}, but got {}", expected, token.lexeme))
+                    .with_code("E201")
+                    .with_primary(token.span, format!("expected {}", expected))
+            }
+            ParserError::UnexpectedEOF(expected, token) => Diagnostic::error(format!(
+                "Expected {}, but reached the end of input",
+                expected
+            ))
+            .with_code("E202")
+            .with_primary(token.span, format!("expected {}", expected)),
+            ParserError::InvalidAssignmentTarget(equals) => {
+                Diagnostic::error("Invalid assignment target")
+                    .with_code("E203")
+                    .with_primary(equals.span, "can't assign to the left-hand side")
+                    .with_note("only variables and properties can be assigned to")
+            }
+            ParserError::TooMany(what, token) => {
+                Diagnostic::error(format!("Can't have more than {} {}", MAX_ARITY, what))
+                    .with_code("E204")
+                    .with_primary(token.span, format!("{} past the limit", what))
+            }
+        }
+    }
+}
+
+type ParserResult<'a> = Result<ExprId, ParserError<'a>>;
+type StmtResult<'a> = Result<StmtId, ParserError<'a>>;
+
+const MAX_ARITY: usize = 255;
 
-pub struct Parser<'a> {
-    tokens: &'a [Token<'a>],
-    current: usize,
+// pulls tokens from its source one at a time as it needs them, so they need not all be
+// scanned, or held, up front
+pub struct Parser<'a, I> {
+    tokens: I,
+    // the token at the cursor, which is EOF once the input is exhausted
+    current: Token<'a>,
+    previous_span: Span,
+    errors: Vec<ParserError<'a>>,
+    ast: Ast<'a>,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a [Token<'a>]) -> Self {
-        Self { tokens, current: 0 }
+impl<'a, I: Iterator<Item = Token<'a>>> Parser<'a, I> {
+    pub fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        let mut tokens = tokens.into_iter();
+        let current = tokens
+            .next()
+            .unwrap_or_else(|| Token::new(TokenType::EOF, "", Span::default()));
+        Self {
+            tokens,
+            current,
+            previous_span: Span::default(),
+            errors: Vec::new(),
+            ast: Ast::new(),
+        }
+    }
+
+    // a single expression, returned with the tree that holds it
+    pub fn parse(mut self) -> Result<(Ast<'a>, ExprId), ParserError<'a>> {
+        let expr = self.expression()?;
+        if self.peek().is_some() {
+            return Err(ParserError::UnexpectedToken(
+                "end of expression",
+                self.current.clone(),
+            ));
+        }
+        match self.errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok((self.ast, expr)),
+        }
+    }
+
+    pub fn parse_program(self) -> Result<Ast<'a>, Vec<ParserError<'a>>> {
+        let (statements, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // keeps going past syntax errors, returning every statement that did parse alongside
+    // every error that was found
+    pub fn parse_partial(mut self) -> (Ast<'a>, Vec<ParserError<'a>>) {
+        while self.peek().is_some() {
+            if let Some(statement) = self.declaration() {
+                self.ast.push_statement(statement);
+            }
+        }
+        (self.ast, self.errors)
+    }
+
+    fn declaration(&mut self) -> Option<StmtId> {
+        match self.try_declaration() {
+            Ok(statement) => Some(statement),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // discards tokens until the start of what is likely the next statement
+    fn synchronize(&mut self) {
+        let mut previous = self.advance();
+        while self.peek().is_some() {
+            if previous.typ == TokenType::SEMICOLON {
+                return;
+            }
+            match self.current.typ {
+                TokenType::CLASS
+                | TokenType::FUN
+                | TokenType::VAR
+                | TokenType::FOR
+                | TokenType::IF
+                | TokenType::WHILE
+                | TokenType::PRINT
+                | TokenType::RETURN => return,
+                _ => previous = self.advance(),
+            }
+        }
+    }
+
+    fn try_declaration(&mut self) -> StmtResult<'a> {
+        let doc = self.doc();
+        if let Some(keyword) = self.advance_if_match(&TokenType::CLASS) {
+            self.class_declaration(keyword, doc)
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::FUN) {
+            let function = self.function("function name", keyword.span, doc)?;
+            Ok(self.ast.add_stmt(Stmt::Function(function)))
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::VAR) {
+            self.var_declaration(keyword, doc)
+        } else {
+            self.statement()
+        }
+    }
+
+    fn class_declaration(
+        &mut self,
+        keyword: Token<'a>,
+        doc: Option<Cow<'a, str>>,
+    ) -> StmtResult<'a> {
+        let name = self.consume(&TokenType::IDENTIFIER, "class name")?;
+        let super_class = if self.advance_if_match(&TokenType::LESS).is_some() {
+            let name = self.consume(&TokenType::IDENTIFIER, "superclass name")?;
+            Some(self.ast.add_expr(Expr::Variable { name }))
+        } else {
+            None
+        };
+        self.consume(&TokenType::LEFT_BRACE, "'{' before class body")?;
+
+        let mut methods = Vec::new();
+        while self.peek().is_some() && !self.check(&TokenType::RIGHT_BRACE) {
+            let doc = self.doc();
+            let start = self.current.span;
+            let method = self.function("method name", start, doc)?;
+            methods.push(self.ast.add_stmt(Stmt::Function(method)));
+        }
+        let brace = self.consume(&TokenType::RIGHT_BRACE, "'}' after class body")?;
+
+        Ok(self.ast.add_stmt(Stmt::Class {
+            name,
+            super_class,
+            methods,
+            doc,
+            span: keyword.span.to(brace.span),
+        }))
+    }
+
+    // `start` is where the declaration starts, which for a method is its name
+    fn function(
+        &mut self,
+        kind: &'static str,
+        start: Span,
+        doc: Option<Cow<'a, str>>,
+    ) -> Result<stmt::Function<'a>, ParserError<'a>> {
+        let name = self.consume(&TokenType::IDENTIFIER, kind)?;
+        self.consume(&TokenType::LEFT_PAREN, "'(' after function name")?;
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RIGHT_PAREN) {
+            loop {
+                if params.len() == MAX_ARITY {
+                    self.errors
+                        .push(ParserError::TooMany("parameters", self.current.clone()));
+                }
+                params.push(self.consume(&TokenType::IDENTIFIER, "parameter name")?);
+                if self.advance_if_match(&TokenType::COMMA).is_none() {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RIGHT_PAREN, "')' after parameters")?;
+        self.consume(&TokenType::LEFT_BRACE, "'{' before function body")?;
+        let body = self.block()?;
+        Ok(stmt::Function {
+            name,
+            params,
+            body,
+            doc,
+            span: start.to(self.previous_span()),
+        })
     }
 
-    pub fn parse(mut self) -> ParserResult<'a> {
-        self.expression()
+    fn var_declaration(&mut self, keyword: Token<'a>, doc: Option<Cow<'a, str>>) -> StmtResult<'a> {
+        let name = self.consume(&TokenType::IDENTIFIER, "variable name")?;
+        let initializer = if self.advance_if_match(&TokenType::EQUAL).is_some() {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        let semicolon = self.consume(&TokenType::SEMICOLON, "';' after variable declaration")?;
+        Ok(self.ast.add_stmt(Stmt::Var {
+            name,
+            initializer,
+            doc,
+            span: keyword.span.to(semicolon.span),
+        }))
+    }
+
+    fn statement(&mut self) -> StmtResult<'a> {
+        if let Some(keyword) = self.advance_if_match(&TokenType::FOR) {
+            self.for_statement(keyword)
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::IF) {
+            self.if_statement(keyword)
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::PRINT) {
+            self.print_statement(keyword)
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::RETURN) {
+            self.return_statement(keyword)
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::WHILE) {
+            self.while_statement(keyword)
+        } else if let Some(brace) = self.advance_if_match(&TokenType::LEFT_BRACE) {
+            let statements = self.block()?;
+            Ok(self.ast.add_stmt(Stmt::Block {
+                statements,
+                span: brace.span.to(self.previous_span()),
+            }))
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    // desugars `for (initializer; condition; increment) body` into
+    // `{ initializer; while (condition) { body; increment; } }`
+    fn for_statement(&mut self, keyword: Token<'a>) -> StmtResult<'a> {
+        self.consume(&TokenType::LEFT_PAREN, "'(' after 'for'")?;
+        let initializer = if self.advance_if_match(&TokenType::SEMICOLON).is_some() {
+            None
+        } else if let Some(keyword) = self.advance_if_match(&TokenType::VAR) {
+            Some(self.var_declaration(keyword, None)?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(&TokenType::SEMICOLON) {
+            self.ast.add_expr(Expr::Literal {
+                value: Literal::True,
+                span: self.current.span,
+            })
+        } else {
+            self.expression()?
+        };
+        self.consume(&TokenType::SEMICOLON, "';' after loop condition")?;
+
+        let increment = if self.check(&TokenType::RIGHT_PAREN) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(&TokenType::RIGHT_PAREN, "')' after for clauses")?;
+
+        let mut body = self.statement()?;
+        // every desugared node spans the whole `for` statement
+        let span = keyword.span.to(self.previous_span());
+        if let Some(increment) = increment {
+            let increment = self.ast.add_stmt(Stmt::Expression {
+                expression: increment,
+                span: self.ast.expr_span(increment),
+            });
+            body = self.ast.add_stmt(Stmt::Block {
+                statements: vec![body, increment],
+                span,
+            });
+        }
+        body = self.ast.add_stmt(Stmt::While {
+            condition,
+            body,
+            span,
+        });
+        if let Some(initializer) = initializer {
+            body = self.ast.add_stmt(Stmt::Block {
+                statements: vec![initializer, body],
+                span,
+            });
+        }
+        Ok(body)
+    }
+
+    fn if_statement(&mut self, keyword: Token<'a>) -> StmtResult<'a> {
+        self.consume(&TokenType::LEFT_PAREN, "'(' after 'if'")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RIGHT_PAREN, "')' after if condition")?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.advance_if_match(&TokenType::ELSE).is_some() {
+            Some(self.statement()?)
+        } else {
+            None
+        };
+        Ok(self.ast.add_stmt(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            span: keyword.span.to(self.previous_span()),
+        }))
+    }
+
+    fn print_statement(&mut self, keyword: Token<'a>) -> StmtResult<'a> {
+        let expression = self.expression()?;
+        let semicolon = self.consume(&TokenType::SEMICOLON, "';' after value")?;
+        Ok(self.ast.add_stmt(Stmt::Print {
+            expression,
+            span: keyword.span.to(semicolon.span),
+        }))
+    }
+
+    fn return_statement(&mut self, keyword: Token<'a>) -> StmtResult<'a> {
+        let value = if self.check(&TokenType::SEMICOLON) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        let semicolon = self.consume(&TokenType::SEMICOLON, "';' after return value")?;
+        Ok(self.ast.add_stmt(Stmt::Return {
+            span: keyword.span.to(semicolon.span),
+            keyword,
+            value,
+        }))
+    }
+
+    fn while_statement(&mut self, keyword: Token<'a>) -> StmtResult<'a> {
+        self.consume(&TokenType::LEFT_PAREN, "'(' after 'while'")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RIGHT_PAREN, "')' after condition")?;
+        let body = self.statement()?;
+        Ok(self.ast.add_stmt(Stmt::While {
+            condition,
+            body,
+            span: keyword.span.to(self.previous_span()),
+        }))
+    }
+
+    fn block(&mut self) -> Result<Vec<StmtId>, ParserError<'a>> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() && !self.check(&TokenType::RIGHT_BRACE) {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+        self.consume(&TokenType::RIGHT_BRACE, "'}' after block")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> StmtResult<'a> {
+        let expression = self.expression()?;
+        let semicolon = self.consume(&TokenType::SEMICOLON, "';' after expression")?;
+        Ok(self.ast.add_stmt(Stmt::Expression {
+            expression,
+            span: self.ast.expr_span(expression).to(semicolon.span),
+        }))
     }
 
     fn expression(&mut self) -> ParserResult<'a> {
-        self.equality()
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> ParserResult<'a> {
+        let expr = self.or()?;
+        if let Some(equals) = self.advance_if_match(&TokenType::EQUAL) {
+            let value = self.assignment()?;
+            // the target's node becomes the assignment, so that no orphan is left behind
+            let target = self.ast.expr_mut(expr);
+            match target {
+                Expr::Variable { name } => {
+                    let name = name.clone();
+                    *target = Expr::Assign { name, value };
+                }
+                Expr::Get { object, name } => {
+                    let (object, name) = (*object, name.clone());
+                    *target = Expr::Set {
+                        object,
+                        name,
+                        value,
+                    };
+                }
+                // the parser isn't confused about where it is, so no need to synchronize
+                _ => self
+                    .errors
+                    .push(ParserError::InvalidAssignmentTarget(equals)),
+            }
+            return Ok(expr);
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> ParserResult<'a> {
+        let mut expr = self.and()?;
+        while let Some(operator) = self.advance_if_match(&TokenType::OR) {
+            let right = self.and()?;
+            expr = self.ast.add_expr(Expr::Logical {
+                left: expr,
+                operator,
+                right,
+            });
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> ParserResult<'a> {
+        let mut expr = self.equality()?;
+        while let Some(operator) = self.advance_if_match(&TokenType::AND) {
+            let right = self.equality()?;
+            expr = self.ast.add_expr(Expr::Logical {
+                left: expr,
+                operator,
+                right,
+            });
+        }
+        Ok(expr)
     }
 
     fn equality(&mut self) -> ParserResult<'a> {
         let mut expr = self.comparison()?;
-        while let Some(t) = self
-            .peek()
-            .filter(|t| matches!(t.typ, TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL))
-        {
-            self.advance();
-            let operator = t;
+        while matches!(
+            self.current.typ,
+            TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL
+        ) {
+            let operator = self.advance();
             let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.ast.add_expr(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            };
+                right,
+            });
         }
         Ok(expr)
     }
 
     fn comparison(&mut self) -> ParserResult<'a> {
         let mut expr = self.term()?;
-        while let Some(t) = self.peek().filter(|t| {
-            matches!(
-                t.typ,
-                TokenType::GREATER
-                    | TokenType::GREATER_EQUAL
-                    | TokenType::LESS
-                    | TokenType::LESS_EQUAL
-            )
-        }) {
-            let operator = t;
-            self.advance();
+        while matches!(
+            self.current.typ,
+            TokenType::GREATER | TokenType::GREATER_EQUAL | TokenType::LESS | TokenType::LESS_EQUAL
+        ) {
+            let operator = self.advance();
             let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.ast.add_expr(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            };
+                right,
+            });
         }
         Ok(expr)
     }
 
     fn term(&mut self) -> ParserResult<'a> {
         let mut expr = self.factor()?;
-        while let Some(t) = self
-            .peek()
-            .filter(|t| matches!(t.typ, TokenType::MINUS | TokenType::PLUS))
-        {
-            self.advance();
-            let operator = t;
+        while matches!(self.current.typ, TokenType::MINUS | TokenType::PLUS) {
+            let operator = self.advance();
             let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.ast.add_expr(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            };
+                right,
+            });
         }
         Ok(expr)
     }
 
     fn factor(&mut self) -> ParserResult<'a> {
         let mut expr = self.unary()?;
-        while let Some(t) = self
-            .peek()
-            .filter(|t| matches!(t.typ, TokenType::SLASH | TokenType::STAR))
-        {
-            self.advance();
-            let operator = t;
+        while matches!(self.current.typ, TokenType::SLASH | TokenType::STAR) {
+            let operator = self.advance();
             let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.ast.add_expr(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            };
+                right,
+            });
         }
         Ok(expr)
     }
 
     fn unary(&mut self) -> ParserResult<'a> {
-        if let Some(t) = self
-            .peek()
-            .filter(|t| matches!(t.typ, TokenType::BANG | TokenType::MINUS))
-        {
-            self.advance();
-            let operator = t;
+        if matches!(self.current.typ, TokenType::BANG | TokenType::MINUS) {
+            let operator = self.advance();
             let right = self.unary()?;
-            return Ok(Expr::Unary {
-                operator,
-                right: Box::new(right),
-            });
+            return Ok(self.ast.add_expr(Expr::Unary { operator, right }));
         }
-        self.primary()
+        self.call()
     }
 
-    fn primary(&mut self) -> ParserResult<'a> {
-        if let Some(t) = self.peek() {
-            match t.typ {
-                TokenType::FALSE => {
-                    self.advance();
-                    Ok(Expr::Literal {
-                        value: Literal::False,
-                    })
-                }
-                TokenType::TRUE => {
-                    self.advance();
-                    Ok(Expr::Literal {
-                        value: Literal::True,
-                    })
-                }
-                TokenType::NIL => {
-                    self.advance();
-                    Ok(Expr::Literal {
-                        value: Literal::Nil,
-                    })
+    fn call(&mut self) -> ParserResult<'a> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.advance_if_match(&TokenType::LEFT_PAREN).is_some() {
+                expr = self.finish_call(expr)?;
+            } else if self.advance_if_match(&TokenType::DOT).is_some() {
+                let name = self.consume(&TokenType::IDENTIFIER, "property name after '.'")?;
+                expr = self.ast.add_expr(Expr::Get { object: expr, name });
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: ExprId) -> ParserResult<'a> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RIGHT_PAREN) {
+            loop {
+                if arguments.len() == MAX_ARITY {
+                    self.errors
+                        .push(ParserError::TooMany("arguments", self.current.clone()));
                 }
-                TokenType::NUMBER(n) => {
-                    self.advance();
-                    Ok(Expr::Literal {
-                        value: Literal::Number(n),
-                    })
+                arguments.push(self.expression()?);
+                if self.advance_if_match(&TokenType::COMMA).is_none() {
+                    break;
                 }
-                TokenType::STRING(s) => {
-                    self.advance();
-                    Ok(Expr::Literal {
-                        value: Literal::String(s),
-                    })
+            }
+        }
+        let paren = self.consume(&TokenType::RIGHT_PAREN, "')' after arguments")?;
+        Ok(self.ast.add_expr(Expr::Call {
+            callee,
+            paren,
+            arguments,
+        }))
+    }
+
+    fn primary(&mut self) -> ParserResult<'a> {
+        let value = match &self.current.typ {
+            TokenType::FALSE => Literal::False,
+            TokenType::TRUE => Literal::True,
+            TokenType::NIL => Literal::Nil,
+            TokenType::NUMBER(n) => Literal::Number(*n),
+            TokenType::STRING(s) => Literal::String(s.clone()),
+            TokenType::INTERPOLATION(_) => return self.interpolation(),
+            TokenType::SUPER => {
+                let keyword = self.advance();
+                self.consume(&TokenType::DOT, "'.' after 'super'")?;
+                let method = self.consume(&TokenType::IDENTIFIER, "superclass method name")?;
+                return Ok(self.ast.add_expr(Expr::Super { keyword, method }));
+            }
+            TokenType::THIS => {
+                let keyword = self.advance();
+                return Ok(self.ast.add_expr(Expr::This { keyword }));
+            }
+            TokenType::IDENTIFIER => {
+                let name = self.advance();
+                return Ok(self.ast.add_expr(Expr::Variable { name }));
+            }
+            TokenType::LEFT_PAREN => {
+                let opening = self.advance();
+                let expr = self.expression()?;
+                let paren = self.consume(&TokenType::RIGHT_PAREN, "')' after expression")?;
+                return Ok(self.ast.add_expr(Expr::Grouping {
+                    expression: expr,
+                    span: opening.span.to(paren.span),
+                }));
+            }
+            TokenType::EOF => {
+                return Err(ParserError::UnexpectedEOF(
+                    "expression",
+                    self.current.clone(),
+                ))
+            }
+            _ => {
+                return Err(ParserError::UnexpectedToken(
+                    "expression",
+                    self.current.clone(),
+                ))
+            }
+        };
+        let literal = self.advance();
+        Ok(self.ast.add_expr(Expr::Literal {
+            value,
+            span: literal.span,
+        }))
+    }
+
+    // "a ${b} c" arrives as INTERPOLATION("a "), the tokens of `b`, then STRING(" c")
+    fn interpolation(&mut self) -> ParserResult<'a> {
+        let opening = self.current.span;
+        let mut parts = Vec::new();
+        let mut first = true;
+        loop {
+            // every segment after the first resumes the string at the `}` closing an interpolation
+            let resumes = first || self.current.lexeme.starts_with('}');
+            first = false;
+            let (s, last) = match &self.current.typ {
+                TokenType::INTERPOLATION(s) if resumes => (s.clone(), false),
+                TokenType::STRING(s) if resumes => (s.clone(), true),
+                TokenType::EOF => {
+                    return Err(ParserError::UnexpectedEOF(
+                        "end of string",
+                        self.current.clone(),
+                    ))
                 }
-                TokenType::LEFT_PAREN => {
-                    self.advance();
-                    let expr = self.expression()?;
-                    if let Some(t) = self.peek() {
-                        if matches!(t.typ, TokenType::RIGHT_PAREN) {
-                            self.advance();
-                            Ok(Expr::Grouping {
-                                expression: Box::new(expr),
-                            })
-                        } else {
-                            Err(ParserError::UnexpectedToken(")", t))
-                        }
-                    } else {
-                        Err(ParserError::UnexpectedEOF(
-                            "Expected ')' after grouping expression but no token left!",
-                        ))
-                    }
+                _ => {
+                    return Err(ParserError::UnexpectedToken(
+                        "'}' after interpolated expression",
+                        self.current.clone(),
+                    ))
                 }
-                _ => Err(ParserError::UnexpectedToken("literal", t)),
+            };
+            let segment = self.advance();
+            if !s.is_empty() {
+                parts.push(self.ast.add_expr(Expr::Literal {
+                    value: Literal::String(s),
+                    span: segment.span,
+                }));
             }
-        } else {
-            Err(ParserError::UnexpectedEOF("No token left!"))
+            if last {
+                return Ok(self.ast.add_expr(Expr::Interpolation {
+                    parts,
+                    span: opening.to(segment.span),
+                }));
+            }
+            // segments resuming a string start at the `}` closing the interpolation
+            if self.peek().is_some_and(|t| t.lexeme.starts_with('}')) {
+                return Err(ParserError::UnexpectedToken(
+                    "expression",
+                    self.current.clone(),
+                ));
+            }
+            parts.push(self.expression()?);
         }
     }
 
-    fn peek(&self) -> Option<&'a Token<'a>> {
-        let t = self.tokens.get(self.current);
-        if t.is_some_and(|t| matches!(t.typ, TokenType::EOF)) {
-            return None;
+    fn check(&self, typ: &TokenType) -> bool {
+        self.peek().is_some_and(|t| &t.typ == typ)
+    }
+
+    fn advance_if_match(&mut self, typ: &TokenType) -> Option<Token<'a>> {
+        self.check(typ).then(|| self.advance())
+    }
+
+    fn consume(
+        &mut self,
+        typ: &TokenType,
+        expected: &'static str,
+    ) -> Result<Token<'a>, ParserError<'a>> {
+        if self.check(typ) {
+            Ok(self.advance())
+        } else if self.peek().is_some() {
+            Err(ParserError::UnexpectedToken(expected, self.current.clone()))
+        } else {
+            Err(ParserError::UnexpectedEOF(expected, self.current.clone()))
         }
-        t
     }
 
-    fn advance(&mut self) -> Option<&'a Token<'a>> {
-        let c = self.tokens.get(self.current);
-        self.current += 1;
-        c
+    // the doc comment the scanner attached to the token at the cursor, taken for the
+    // declaration it documents
+    fn doc(&mut self) -> Option<Cow<'a, str>> {
+        self.peek()?;
+        self.current.take_doc()
+    }
+
+    fn previous_span(&self) -> Span {
+        self.previous_span
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        Some(&self.current).filter(|t| !matches!(t.typ, TokenType::EOF))
+    }
+
+    // moves the cursor on and returns the token it was at; at the end of input it stays on
+    // EOF, which is also made up if the tokens run out without one
+    fn advance(&mut self) -> Token<'a> {
+        if self.peek().is_none() {
+            return self.current.clone();
+        }
+        let next = self
+            .tokens
+            .next()
+            .unwrap_or_else(|| Token::new(TokenType::EOF, "", self.current.span));
+        let token = std::mem::replace(&mut self.current, next);
+        self.previous_span = token.span;
+        token
     }
 }
@@ -0,0 +1,171 @@
+use crate::scanner::{ScanError, Scanner};
+use crate::token::Token;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+// how much input is asked of the reader at a time
+const READ_SIZE: usize = 64 * 1024;
+
+type Scanned<'a> = Vec<Result<Token<'a>, ScanError<'a>>>;
+
+// scans a `Read` source such as a pipe or a large file a run of whole lines at a time, so
+// only the current chunk of source and its tokens are held in memory. As an iterator it
+// gives the same tokens and errors as `Scanner`, made to own their text; a failed read ends
+// it early and is kept for `take_error`
+pub struct ReadScanner<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    // bytes at the front of the buffer that were handed out with the last chunk
+    scanned: usize,
+    // byte offset and line of the buffer's first byte in the whole input
+    offset: usize,
+    line: usize,
+    after_operand: bool,
+    lossless: bool,
+    // the reader has nothing more to give
+    exhausted: bool,
+    // the EOF token has been handed out, or reading failed
+    finished: bool,
+    // the last chunk had nowhere to stop, so more input is needed before scanning again
+    starved: bool,
+    // what the iterator has scanned but not yet handed out
+    pending: VecDeque<Result<Token<'static>, ScanError<'static>>>,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> ReadScanner<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            scanned: 0,
+            offset: 0,
+            line: 1,
+            after_operand: false,
+            lossless: false,
+            exhausted: false,
+            finished: false,
+            starved: false,
+            pending: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    // keep whitespace and comments on the tokens, as `Scanner::with_trivia` does
+    pub fn with_trivia(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+
+    // the tokens and errors of the next run of lines, None once the EOF token was handed out.
+    // They borrow the scanner's buffer, which is reused by the next call. A chunk is empty
+    // when a string or comment runs on past everything read so far.
+    pub fn next_chunk(&mut self) -> io::Result<Option<(Vec<Token<'_>>, Vec<ScanError<'_>>)>> {
+        let Some(items) = self.chunk()? else {
+            return Ok(None);
+        };
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for item in items {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(e) => errors.push(e),
+            }
+        }
+        Ok(Some((tokens, errors)))
+    }
+
+    // the error that cut iteration short, if a read failed
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    // what the next run of lines scans to, in source order
+    fn chunk(&mut self) -> io::Result<Option<Scanned<'_>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        self.buffer.drain(..self.scanned);
+        self.offset += self.scanned;
+        self.scanned = 0;
+
+        // a chunk with nowhere to stop is scanned again from its start once more has been
+        // read, so the buffer at least doubles first to keep rescanning linear overall
+        if self.starved {
+            let target = self.buffer.len() * 2;
+            while !self.exhausted && self.buffer.len() < target {
+                self.fill()?;
+            }
+            self.starved = false;
+        }
+        let mut searched = 0;
+        while !self.exhausted && !self.buffer[searched..].contains(&b'\n') {
+            searched = self.buffer.len();
+            self.fill()?;
+        }
+
+        // a chunk ends with a newline, so it never splits a character or a line
+        let end = match self.buffer.iter().rposition(|&b| b == b'\n') {
+            Some(newline) if !self.exhausted => newline + 1,
+            _ => self.buffer.len(),
+        };
+        let source = std::str::from_utf8(&self.buffer[..end])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut scanner = Scanner::resume(source, self.offset, self.line, self.after_operand);
+        if self.lossless {
+            scanner = scanner.with_trivia();
+        }
+
+        if self.exhausted {
+            self.finished = true;
+            self.scanned = end;
+            return Ok(Some(scanner.collect()));
+        }
+        match scanner.scan_chunk() {
+            Some((scanner, checkpoint)) => {
+                self.scanned = checkpoint.position;
+                self.line = checkpoint.line;
+                self.after_operand = checkpoint.after_operand;
+                Ok(Some(scanner.collect()))
+            }
+            None => {
+                self.starved = true;
+                Ok(Some(Vec::new()))
+            }
+        }
+    }
+
+    // reads up to READ_SIZE more bytes, however few each read of the reader gives
+    fn fill(&mut self) -> io::Result<()> {
+        let read = (&mut self.reader)
+            .take(READ_SIZE as u64)
+            .read_to_end(&mut self.buffer)?;
+        if read < READ_SIZE {
+            self.exhausted = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ReadScanner<R> {
+    type Item = Result<Token<'static>, ScanError<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let items = match self.chunk() {
+                Ok(Some(items)) => items
+                    .into_iter()
+                    .map(|item| item.map(Token::into_owned).map_err(ScanError::into_owned))
+                    .collect::<Vec<_>>(),
+                Ok(None) => return None,
+                Err(e) => {
+                    self.error = Some(e);
+                    self.finished = true;
+                    return None;
+                }
+            };
+            self.pending.extend(items);
+        }
+        self.pending.pop_front()
+    }
+}
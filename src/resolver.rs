@@ -0,0 +1,310 @@
+use crate::ast::{Ast, ExprId, SideTable, StmtId};
+use crate::diagnostics::Diagnostic;
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::visit::{walk_expr, walk_stmt, ExprVisitor, StmtVisitor};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ResolverError<'a> {
+    #[error("Can't read local variable {} in its own initializer at {}!", .0.name(), .0.span)]
+    OwnInitializer(&'a Token<'a>),
+    #[error("Already a variable named {} in this scope at {}!", .0.name(), .0.span)]
+    AlreadyDefined(&'a Token<'a>),
+    #[error("Can't return from top-level code at {}!", .0.span)]
+    TopLevelReturn(&'a Token<'a>),
+    #[error("Can't return a value from an initializer at {}!", .0.span)]
+    InitializerReturn(&'a Token<'a>),
+    #[error("Can't use 'this' outside of a class at {}!", .0.span)]
+    ThisOutsideClass(&'a Token<'a>),
+    #[error("A class can't inherit from itself at {}!", .0.span)]
+    InheritFromSelf(&'a Token<'a>),
+    #[error("Can't use 'super' outside of a class at {}!", .0.span)]
+    SuperOutsideClass(&'a Token<'a>),
+    #[error("Can't use 'super' in a class with no superclass at {}!", .0.span)]
+    SuperWithoutSuperclass(&'a Token<'a>),
+}
+
+impl<'a> ResolverError<'a> {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            ResolverError::OwnInitializer(name) => Diagnostic::error(format!(
+                "Can't read local variable {} in its own initializer",
+                name.name()
+            ))
+            .with_code("E301")
+            .with_primary(name.span, "read before it is initialized"),
+            ResolverError::AlreadyDefined(name) => Diagnostic::error(format!(
+                "Already a variable named {} in this scope",
+                name.name()
+            ))
+            .with_code("E302")
+            .with_primary(name.span, "declared again here"),
+            ResolverError::TopLevelReturn(keyword) => {
+                Diagnostic::error("Can't return from top-level code")
+                    .with_code("E303")
+                    .with_primary(keyword.span, "not inside a function")
+            }
+            ResolverError::InitializerReturn(keyword) => {
+                Diagnostic::error("Can't return a value from an initializer")
+                    .with_code("E304")
+                    .with_primary(keyword.span, "returns a value")
+                    .with_note("an initializer always returns 'this'")
+            }
+            ResolverError::ThisOutsideClass(keyword) => {
+                Diagnostic::error("Can't use 'this' outside of a class")
+                    .with_code("E305")
+                    .with_primary(keyword.span, "not inside a method")
+            }
+            ResolverError::InheritFromSelf(name) => {
+                Diagnostic::error("A class can't inherit from itself")
+                    .with_code("E306")
+                    .with_primary(name.span, "names the class being declared")
+            }
+            ResolverError::SuperOutsideClass(keyword) => {
+                Diagnostic::error("Can't use 'super' outside of a class")
+                    .with_code("E307")
+                    .with_primary(keyword.span, "not inside a method")
+            }
+            ResolverError::SuperWithoutSuperclass(keyword) => {
+                Diagnostic::error("Can't use 'super' in a class with no superclass")
+                    .with_code("E308")
+                    .with_primary(keyword.span, "there is no superclass to look up")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// `'r` borrows the tree being resolved
+pub struct Resolver<'r, 'a> {
+    ast: &'r Ast<'a>,
+    // scope distances of the variable, assignment, `this` and `super` expressions that
+    // resolve to a local
+    locals: SideTable<ExprId, usize>,
+    // one map per enclosing block scope, where `false` means declared but not yet initialized
+    scopes: Vec<HashMap<&'r str, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ResolverError<'r>>,
+}
+
+impl<'r, 'a> Resolver<'r, 'a> {
+    pub fn new(ast: &'r Ast<'a>) -> Self {
+        Self {
+            ast,
+            locals: SideTable::new(),
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(mut self) -> Result<SideTable<ExprId, usize>, Vec<ResolverError<'r>>> {
+        self.visit_program(self.ast);
+        self.finish()
+    }
+
+    pub fn resolve_expression(
+        mut self,
+        expr: ExprId,
+    ) -> Result<SideTable<ExprId, usize>, Vec<ResolverError<'r>>> {
+        self.visit_expr(self.ast, expr);
+        self.finish()
+    }
+
+    fn finish(self) -> Result<SideTable<ExprId, usize>, Vec<ResolverError<'r>>> {
+        if self.errors.is_empty() {
+            Ok(self.locals)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_function(&mut self, params: &'r [Token<'a>], body: &'r [StmtId], typ: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = typ;
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        for &statement in body {
+            self.visit_stmt(self.ast, statement);
+        }
+        self.end_scope();
+        self.current_function = enclosing_function;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &'r Token<'a>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.insert(name.name(), false).is_some() {
+                self.errors.push(ResolverError::AlreadyDefined(name));
+            }
+        }
+    }
+
+    fn define(&mut self, name: &'r Token<'a>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.name(), true);
+        }
+    }
+
+    // names not found in any enclosing scope are left unresolved and treated as globals
+    fn resolve_local(&mut self, expr: ExprId, name: &Token<'a>) {
+        if let Some(depth) = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name.name()))
+        {
+            self.locals.insert(expr, depth);
+        }
+    }
+}
+
+impl<'r, 'a> ExprVisitor<'r, 'a> for Resolver<'r, 'a> {
+    fn visit_expr(&mut self, ast: &'r Ast<'a>, id: ExprId) {
+        match &ast[id] {
+            Expr::Assign { name, value } => {
+                self.visit_expr(ast, *value);
+                self.resolve_local(id, name);
+            }
+            Expr::Super { keyword, .. } => {
+                match self.current_class {
+                    ClassType::None => self.errors.push(ResolverError::SuperOutsideClass(keyword)),
+                    ClassType::Class => self
+                        .errors
+                        .push(ResolverError::SuperWithoutSuperclass(keyword)),
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local(id, keyword);
+            }
+            Expr::This { keyword } => {
+                if self.current_class == ClassType::None {
+                    self.errors.push(ResolverError::ThisOutsideClass(keyword));
+                    return;
+                }
+                self.resolve_local(id, keyword);
+            }
+            Expr::Variable { name } => {
+                if self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(name.name()))
+                    .is_some_and(|defined| !defined)
+                {
+                    self.errors.push(ResolverError::OwnInitializer(name));
+                }
+                self.resolve_local(id, name);
+            }
+            _ => walk_expr(self, ast, id),
+        }
+    }
+}
+
+impl<'r, 'a> StmtVisitor<'r, 'a> for Resolver<'r, 'a> {
+    fn visit_stmt(&mut self, ast: &'r Ast<'a>, id: StmtId) {
+        match &ast[id] {
+            Stmt::Block { statements, .. } => {
+                self.begin_scope();
+                for &statement in statements {
+                    self.visit_stmt(ast, statement);
+                }
+                self.end_scope();
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                ..
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+                self.declare(name);
+                self.define(name);
+
+                if let Some(super_class) = *super_class {
+                    if let Expr::Variable { name: super_name } = &ast[super_class] {
+                        if super_name.name() == name.name() {
+                            self.errors.push(ResolverError::InheritFromSelf(super_name));
+                        }
+                        self.current_class = ClassType::Subclass;
+                        self.resolve_local(super_class, super_name);
+                    }
+
+                    self.begin_scope();
+                    self.scopes.last_mut().unwrap().insert("super", true);
+                }
+
+                self.begin_scope();
+                self.scopes.last_mut().unwrap().insert("this", true);
+                for method in methods.iter().filter_map(|&method| ast.function(method)) {
+                    let declaration = if method.name.name() == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.resolve_function(&method.params, &method.body, declaration);
+                }
+                self.end_scope();
+
+                if super_class.is_some() {
+                    self.end_scope();
+                }
+                self.current_class = enclosing_class;
+            }
+            Stmt::Function(function) => {
+                self.declare(&function.name);
+                self.define(&function.name);
+                self.resolve_function(&function.params, &function.body, FunctionType::Function);
+            }
+            Stmt::Return { keyword, value, .. } => {
+                if self.current_function == FunctionType::None {
+                    self.errors.push(ResolverError::TopLevelReturn(keyword));
+                }
+                if let Some(value) = *value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.errors.push(ResolverError::InitializerReturn(keyword));
+                    }
+                    self.visit_expr(ast, value);
+                }
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                self.declare(name);
+                if let Some(initializer) = *initializer {
+                    self.visit_expr(ast, initializer);
+                }
+                self.define(name);
+            }
+            _ => walk_stmt(self, ast, id),
+        }
+    }
+}
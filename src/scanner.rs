@@ -1,94 +1,440 @@
+use crate::diagnostics::Diagnostic;
+use crate::span::Span;
 use crate::token::Token;
 use crate::token_type::TokenType;
-use crate::utils::take_slice;
-use unicode_segmentation::UnicodeSegmentation;
+use crate::trivia::{Trivia, TriviaKind};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use thiserror::Error;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ScanError<'a> {
+    #[error("Unexpected character '{0}' at {1}!")]
+    UnexpectedCharacter(Cow<'a, str>, Span),
+    #[error("Unterminated string starting at {0}!")]
+    UnterminatedString(Span),
+    #[error("Unterminated /* */ comment starting at {0}!")]
+    UnterminatedComment(Span),
+    #[error("Invalid escape sequence '\\{0}' at {1}!")]
+    InvalidEscape(Cow<'a, str>, Span),
+    #[error("Invalid unicode escape sequence at {0}!")]
+    InvalidUnicodeEscape(Span),
+    #[error("Invalid number literal '{0}' at {2}: {1}!")]
+    InvalidNumber(Cow<'a, str>, &'static str, Span),
+}
+
+impl<'a> ScanError<'a> {
+    // a copy of the error that no longer borrows the source
+    pub fn into_owned(self) -> ScanError<'static> {
+        let owned = |text: Cow<'a, str>| Cow::Owned(text.into_owned());
+        match self {
+            ScanError::UnexpectedCharacter(c, span) => {
+                ScanError::UnexpectedCharacter(owned(c), span)
+            }
+            ScanError::UnterminatedString(span) => ScanError::UnterminatedString(span),
+            ScanError::UnterminatedComment(span) => ScanError::UnterminatedComment(span),
+            ScanError::InvalidEscape(c, span) => ScanError::InvalidEscape(owned(c), span),
+            ScanError::InvalidUnicodeEscape(span) => ScanError::InvalidUnicodeEscape(span),
+            ScanError::InvalidNumber(literal, reason, span) => {
+                ScanError::InvalidNumber(owned(literal), reason, span)
+            }
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ScanError::UnexpectedCharacter(_, span)
+            | ScanError::UnterminatedString(span)
+            | ScanError::UnterminatedComment(span)
+            | ScanError::InvalidEscape(_, span)
+            | ScanError::InvalidUnicodeEscape(span)
+            | ScanError::InvalidNumber(_, _, span) => *span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            ScanError::UnexpectedCharacter(c, span) => {
+                Diagnostic::error(format!("Unexpected character '{}'", c))
+                    .with_code("E101")
+                    .with_primary(*span, "not valid in Lox source")
+            }
+            ScanError::UnterminatedString(span) => Diagnostic::error("Unterminated string")
+                .with_code("E102")
+                .with_primary(*span, "string starts here"),
+            ScanError::UnterminatedComment(span) => Diagnostic::error("Unterminated /* */ comment")
+                .with_code("E103")
+                .with_primary(*span, "comment starts here"),
+            ScanError::InvalidEscape(c, span) => {
+                Diagnostic::error(format!("Invalid escape sequence '\\{}'", c))
+                    .with_code("E104")
+                    .with_primary(*span, "unknown escape")
+                    .with_note(r#"valid escapes are \n, \t, \r, \\, \", \$, \0 and \u{XXXX}"#)
+            }
+            ScanError::InvalidUnicodeEscape(span) => {
+                Diagnostic::error("Invalid unicode escape sequence")
+                    .with_code("E105")
+                    .with_primary(*span, "not a unicode scalar value")
+                    .with_note(r#"unicode escapes are written \u{XXXX} with 1 to 6 hex digits"#)
+            }
+            ScanError::InvalidNumber(literal, reason, span) => {
+                Diagnostic::error(format!("Invalid number literal '{}'", literal))
+                    .with_code("E106")
+                    .with_primary(*span, *reason)
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ScanWarning<'a> {
+    #[error("Identifier '{}' at {} looks like '{}' at {}!", .0.name(), .0.span, .1.name(), .1.span)]
+    ConfusableIdentifier(Token<'a>, Token<'a>),
+}
+
+impl<'a> ScanWarning<'a> {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            ScanWarning::ConfusableIdentifier(identifier, other) => Diagnostic::warning(format!(
+                "Identifier '{}' looks like '{}'",
+                identifier.name(),
+                other.name()
+            ))
+            .with_code("W101")
+            .with_primary(identifier.span, "this identifier")
+            .with_secondary(other.span, "is easily confused with this one")
+            .with_note("the two are different variables"),
+        }
+    }
+}
+
+// flags identifiers that are distinct but render alike, such as a Latin 'a' and a Cyrillic
+// 'а', reporting each such name once at its first use
+pub fn confusable_identifiers<'a>(tokens: &[Token<'a>]) -> Vec<ScanWarning<'a>> {
+    let mut confusables = Confusables::default();
+    tokens
+        .iter()
+        .filter_map(|token| confusables.check(token))
+        .collect()
+}
+
+// what `confusable_identifiers` finds, worked out one token at a time for tokens that are
+// not all held at once
+#[derive(Default)]
+pub struct Confusables<'a> {
+    // the first identifier seen with each skeleton
+    seen: HashMap<String, Token<'a>>,
+    reported: HashSet<String>,
+}
+
+impl<'a> Confusables<'a> {
+    pub fn check(&mut self, token: &Token<'a>) -> Option<ScanWarning<'a>> {
+        if token.typ != TokenType::IDENTIFIER {
+            return None;
+        }
+        let skeleton = unicode_security::skeleton(token.name()).collect::<String>();
+        match self.seen.get(&skeleton) {
+            Some(other) if other.name() != token.name() => {
+                if self.reported.insert(token.name().to_string()) {
+                    return Some(ScanWarning::ConfusableIdentifier(
+                        token.clone(),
+                        other.clone(),
+                    ));
+                }
+            }
+            Some(_) => {}
+            None => {
+                self.seen.insert(skeleton, token.clone());
+            }
+        }
+        None
+    }
+}
+
+// a place where scanning a run of lines can stop and later pick up again from scratch: just
+// after a newline, outside any interpolation, with no doc or trivia waiting for a token
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint {
+    pub(crate) position: usize,
+    pub(crate) line: usize,
+    pub(crate) after_operand: bool,
+    // how many tokens and errors had been scanned by then
+    tokens: usize,
+    errors: usize,
+}
 
 pub struct Scanner<'a> {
-    source: Vec<&'a str>,
-    tokens: Vec<Token<'a>>,
+    source: &'a str,
+    // scanned tokens not yet handed out, and how many were handed out before them
+    tokens: VecDeque<Token<'a>>,
+    handed_out: usize,
+    // scanned errors not yet handed out, each with the number of tokens scanned before it
+    errors: VecDeque<(usize, ScanError<'a>)>,
+    finished: bool,
+    // byte offset of the source within a larger input, added to every span
+    offset: usize,
+    // byte offsets into the source
     start: usize,
     current: usize,
     line: usize,
+    // byte offset of the first character on the current line
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
+    // the column, in grapheme clusters, last worked out and the byte offset it is for, so
+    // that columns along a line are counted incrementally
+    column: (usize, usize),
+    // one entry per `${` still open: the unmatched `{` inside it and the span of the opening
+    // quote of the string it belongs to
+    interpolations: Vec<(usize, Span)>,
+    // doc comment waiting to be attached to the next token
+    doc: Option<Cow<'a, str>>,
+    // whether whitespace and comments are kept as trivia on the tokens
+    lossless: bool,
+    // trivia waiting to lead the next token
+    leading: Vec<Trivia<'a>>,
+    // whether a newline separates the cursor from the last token, so new trivia leads the
+    // next token rather than trailing that one
+    line_ended: bool,
+    // byte offset up to which the source is covered by tokens or trivia
+    accounted: usize,
+    // whether the last token can end an operand, which makes a following `.` an access
+    after_operand: bool,
+    checkpoint: Option<Checkpoint>,
 }
 
 impl<'a> Scanner<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
-            source: source.graphemes(true).collect(),
-            tokens: Vec::new(),
+            source,
+            tokens: VecDeque::new(),
+            handed_out: 0,
+            errors: VecDeque::new(),
+            finished: false,
+            offset: 0,
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
+            column: (0, 1),
+            interpolations: Vec::new(),
+            doc: None,
+            lossless: false,
+            leading: Vec::new(),
+            line_ended: true,
+            accounted: 0,
+            after_operand: false,
+            checkpoint: None,
         }
     }
 
-    pub fn scan_tokens(mut self) -> Vec<Token<'a>> {
-        while let Some(_) = self.scan_token() {}
+    // scans a chunk of a larger input that starts at a line beginning at byte `offset`
+    pub(crate) fn resume(source: &'a str, offset: usize, line: usize, after_operand: bool) -> Self {
+        let mut scanner = Self::new(source);
+        scanner.offset = offset;
+        scanner.line = line;
+        scanner.start_line = line;
+        scanner.after_operand = after_operand;
+        scanner
+    }
+
+    // keeps whitespace, comments and unscannable text as trivia on the tokens, so that their
+    // `source_text` joined together gives back the source byte for byte
+    pub fn with_trivia(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
 
-        self.tokens.push(Token::new(TokenType::EOF, "", self.line));
-        self.tokens
+    pub fn scan_tokens(self) -> Result<Vec<Token<'a>>, Vec<ScanError<'a>>> {
+        let (tokens, errors) = self.scan_partial();
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // keeps going past lexical errors, returning every token that did scan alongside every
+    // error that was found
+    pub fn scan_partial(mut self) -> (Vec<Token<'a>>, Vec<ScanError<'a>>) {
+        while self.scan_token().is_some() {}
+        self.finish();
+        // nothing was handed out, so the tokens are still in one piece and are not copied
+        let tokens = Vec::from(self.tokens);
+        let errors = self.errors.into_iter().map(|(_, e)| e).collect();
+        (tokens, errors)
+    }
+
+    // scans the whole source, which holds complete lines of a larger input, and returns what
+    // comes before the last checkpoint, in source order; None if there is no checkpoint to
+    // stop at
+    pub(crate) fn scan_chunk(mut self) -> Option<(Self, Checkpoint)> {
+        while self.scan_token().is_some() {}
+        let checkpoint = self.checkpoint?;
+        self.tokens.truncate(checkpoint.tokens);
+        self.errors.truncate(checkpoint.errors);
+        self.finished = true;
+        Some((self, checkpoint))
+    }
+
+    fn finish(&mut self) {
+        // the string around an unclosed `${` never ended either
+        for (_, opening) in std::mem::take(&mut self.interpolations) {
+            self.error(ScanError::UnterminatedString(opening));
+        }
+
+        self.mark_start();
+        self.add_token(TokenType::EOF);
+        self.finished = true;
+    }
+
+    // the next token or error in source order, once it can be handed out; a token is held
+    // back until the one after it is scanned, so that its trailing trivia is complete
+    fn ready(&mut self) -> Option<Result<Token<'a>, ScanError<'a>>> {
+        if self
+            .errors
+            .front()
+            .is_some_and(|(before, _)| *before == self.handed_out)
+        {
+            return self.errors.pop_front().map(|(_, e)| Err(e));
+        }
+        if self.tokens.len() > 1 || self.finished {
+            let token = self.tokens.pop_front()?;
+            self.handed_out += 1;
+            return Some(Ok(token));
+        }
+        None
     }
 
     fn scan_token(&mut self) -> Option<()> {
-        let line = self.line;
-        self.advance_while(|c| c == " " || c == "\t" || c == "\r" || c == "\n");
+        self.whitespace();
 
-        self.start = self.current;
+        self.mark_start();
         let c = self.advance()?;
+        self.token(c);
+
+        // whatever the scanner gave up on is kept verbatim
+        if self.lossless && self.accounted < self.current {
+            self.add_trivia(TriviaKind::Skipped);
+        }
+        Some(())
+    }
+
+    fn whitespace(&mut self) {
+        loop {
+            // only trivia needs to know where each run of whitespace starts
+            if self.lossless {
+                self.mark_start();
+            }
+            if self.at_newline() {
+                self.advance_newline();
+                self.add_trivia(TriviaKind::Newline);
+            } else if self.at_blank() {
+                // blanks are never newlines, so they can be skipped a byte at a time
+                while self.at_blank() {
+                    self.current += 1;
+                }
+                self.add_trivia(TriviaKind::Whitespace);
+            } else {
+                return;
+            }
+        }
+    }
+
+    // a space, a tab or a carriage return that doesn't start a "\r\n"
+    fn at_blank(&self) -> bool {
+        let bytes = self.source.as_bytes();
+        match bytes.get(self.current) {
+            Some(b' ' | b'\t') => true,
+            Some(b'\r') => bytes.get(self.current + 1) != Some(&b'\n'),
+            _ => false,
+        }
+    }
+
+    fn token(&mut self, c: char) {
         match c {
-            "(" => self.add_token(TokenType::LEFT_PAREN),
-            ")" => self.add_token(TokenType::RIGHT_PAREN),
-            "{" => self.add_token(TokenType::LEFT_BRACE),
-            "}" => self.add_token(TokenType::RIGHT_BRACE),
-            "," => self.add_token(TokenType::COMMA),
-            "." => self.add_token(TokenType::DOT),
-            "-" => self.add_token(TokenType::MINUS),
-            "+" => self.add_token(TokenType::PLUS),
-            ";" => self.add_token(TokenType::SEMICOLON),
-            "*" => self.add_token(TokenType::STAR),
-            "!" => {
-                let token_type = if self.advance_if_match("=") {
+            '(' => self.add_token(TokenType::LEFT_PAREN),
+            ')' => self.add_token(TokenType::RIGHT_PAREN),
+            '{' => {
+                if let Some((depth, _)) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE);
+            }
+            '}' => match self.interpolations.last_mut() {
+                // closes the `${`, so the string picks up where it left off
+                Some((0, opening)) => {
+                    let opening = *opening;
+                    self.interpolations.pop();
+                    self.string(opening);
+                }
+                Some((depth, _)) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RIGHT_BRACE);
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE),
+            },
+            ',' => self.add_token(TokenType::COMMA),
+            '.' => {
+                // `.5` is a number unless the dot could be a property access
+                if !self.after_operand && self.peek().is_some_and(is_digit) {
+                    self.number();
+                } else {
+                    self.add_token(TokenType::DOT);
+                }
+            }
+            '-' => self.add_token(TokenType::MINUS),
+            '+' => self.add_token(TokenType::PLUS),
+            ';' => self.add_token(TokenType::SEMICOLON),
+            '*' => self.add_token(TokenType::STAR),
+            '!' => {
+                let token_type = if self.advance_if_match('=') {
                     TokenType::BANG_EQUAL
                 } else {
                     TokenType::BANG
                 };
                 self.add_token(token_type);
             }
-            "=" => {
-                let token_type = if self.advance_if_match("=") {
+            '=' => {
+                let token_type = if self.advance_if_match('=') {
                     TokenType::EQUAL_EQUAL
                 } else {
                     TokenType::EQUAL
                 };
                 self.add_token(token_type);
             }
-            "<" => {
-                let token_type = if self.advance_if_match("=") {
+            '<' => {
+                let token_type = if self.advance_if_match('=') {
                     TokenType::LESS_EQUAL
                 } else {
                     TokenType::LESS
                 };
                 self.add_token(token_type);
             }
-            ">" => {
-                let token_type = if self.advance_if_match("=") {
+            '>' => {
+                let token_type = if self.advance_if_match('=') {
                     TokenType::GREATER_EQUAL
                 } else {
                     TokenType::GREATER
                 };
                 self.add_token(token_type);
             }
-            "/" => {
-                if self.advance_if_match("/") {
+            '/' => {
+                if self.advance_if_match('/') {
                     self.slash_slash_comment();
-                } else if self.advance_if_match("*") {
-                    self.slash_star_comment()?;
+                } else if self.advance_if_match('*') {
+                    self.slash_star_comment();
                 } else {
                     self.add_token(TokenType::SLASH);
                 }
             }
-            "\"" => {
-                self.string()?;
+            '"' => {
+                let opening = self.opening_span(1);
+                self.string(opening);
             }
             _ => {
                 if is_digit(c) {
@@ -96,32 +442,101 @@ impl<'a> Scanner<'a> {
                 } else if is_alpha(c) {
                     self.identifier();
                 } else {
-                    error(line, format!("Unexpected character '{}'.", c).as_str());
+                    self.unexpected_character();
                 }
             }
         }
-        Some(())
     }
 
-    fn peek(&self) -> Option<&str> {
-        self.source.get(self.current).map(|&c| c)
+    // reports the whole grapheme cluster at the start of the token, such as an emoji with
+    // modifiers
+    fn unexpected_character(&mut self) {
+        let end = self.start + grapheme_len(&self.source[self.start..]);
+        while self.current < end {
+            // will never be None
+            self.advance();
+        }
+        let span = self.span();
+        let c = self.text(self.start, self.current);
+        self.error(ScanError::UnexpectedCharacter(c.into(), span));
     }
 
-    fn peek_next(&self) -> Option<&str> {
-        self.source.get(self.current + 1).map(|&c| c)
+    fn peek(&self) -> Option<char> {
+        self.char_at(self.current)
     }
 
-    fn advance(&mut self) -> Option<&str> {
-        let &c = self.source.get(self.current)?;
-        if c == "\n" {
-            self.line += 1;
+    fn peek_next(&self) -> Option<char> {
+        let c = self.peek()?;
+        self.char_at(self.current + c.len_utf8())
+    }
+
+    // decodes the character starting at byte `position`, which is cheap for ASCII
+    fn char_at(&self, position: usize) -> Option<char> {
+        match self.source.as_bytes().get(position) {
+            Some(&b) if b.is_ascii() => Some(b as char),
+            Some(_) => self.source[position..].chars().next(),
+            None => None,
         }
+    }
 
-        self.current += 1;
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.line_start = self.current;
+        }
         Some(c)
     }
 
-    fn advance_if_match(&mut self, expected: &str) -> bool {
+    // "\r\n" counts as a single newline
+    fn at_newline(&self) -> bool {
+        match self.peek() {
+            Some('\n') => true,
+            Some('\r') => self.peek_next() == Some('\n'),
+            _ => false,
+        }
+    }
+
+    fn advance_newline(&mut self) {
+        self.advance_if_match('\r');
+        self.advance_if_match('\n');
+    }
+
+    fn text(&self, start: usize, end: usize) -> &'a str {
+        &self.source[start..end]
+    }
+
+    fn mark_start(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column_at(self.current);
+    }
+
+    // columns count grapheme clusters from the start of the line
+    fn column_at(&mut self, position: usize) -> usize {
+        let (from, column) = self.column;
+        let (from, column) = if from < self.line_start || from > position {
+            (self.line_start, 1)
+        } else {
+            (from, column)
+        };
+        let column = column + grapheme_count(&self.source[from..position]);
+        self.column = (position, column);
+        column
+    }
+
+    // the span from the start of the current token up to the cursor
+    fn span(&self) -> Span {
+        Span::new(
+            self.offset + self.start,
+            self.offset + self.current,
+            self.start_line,
+            self.start_column,
+        )
+    }
+
+    fn advance_if_match(&mut self, expected: char) -> bool {
         self.peek()
             .is_some_and(|c| c == expected)
             .then(|| {
@@ -131,89 +546,402 @@ impl<'a> Scanner<'a> {
             .is_some()
     }
 
-    fn advance_while(&mut self, predicate: fn(&str) -> bool) {
+    fn advance_while(&mut self, predicate: fn(char) -> bool) {
         while self.peek().is_some_and(predicate) {
             // will never be None
             self.advance();
         }
     }
 
+    // an empty span at the cursor
+    fn cursor_span(&mut self) -> Span {
+        let column = self.column_at(self.current);
+        let position = self.offset + self.current;
+        Span::new(position, position, self.line, column)
+    }
+
+    // the span of the first `len` bytes of the current token, such as an opening delimiter
+    fn opening_span(&self, len: usize) -> Span {
+        Span::new(
+            self.offset + self.start,
+            self.offset + self.start + len,
+            self.start_line,
+            self.start_column,
+        )
+    }
+
     fn add_token(&mut self, typ: TokenType<'a>) {
-        self.tokens.push(Token::new(
-            typ,
-            take_slice(&self.source, self.start, self.current),
-            self.line,
-        ));
+        let mut token = Token::new(typ, self.text(self.start, self.current), self.span());
+        if self.doc.is_some() || !self.leading.is_empty() {
+            let extras = token.extras_mut();
+            extras.doc = self.doc.take();
+            extras.leading = self.leading.len();
+            extras.trivia = self.leading.drain(..).collect();
+        }
+        self.after_operand = matches!(
+            token.typ,
+            TokenType::IDENTIFIER
+                | TokenType::RIGHT_PAREN
+                | TokenType::THIS
+                | TokenType::NUMBER(_)
+                | TokenType::STRING(_)
+        );
+        self.tokens.push_back(token);
+        self.line_ended = false;
+        self.accounted = self.current;
     }
 
-    fn string(&mut self) -> Option<()> {
-        self.advance_while(|c| c != "\"");
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        self.accounted = self.current;
+        if self.lossless {
+            self.keep_trivia(kind);
+        }
+        if kind == TriviaKind::Newline
+            && self.leading.is_empty()
+            && self.interpolations.is_empty()
+            && self.doc.is_none()
+        {
+            self.checkpoint = Some(Checkpoint {
+                position: self.current,
+                line: self.line,
+                after_operand: self.after_operand,
+                tokens: self.tokens.len(),
+                errors: self.errors.len(),
+            });
+        }
+    }
 
-        if self.peek() == None {
-            error(self.line, "Unterminated string.");
-            return None;
+    fn keep_trivia(&mut self, kind: TriviaKind) {
+        let text = self.text(self.start, self.current);
+        let trivia = Trivia::new(kind, text, self.span());
+        match self.tokens.back_mut() {
+            Some(token) if !self.line_ended => token.extras_mut().trivia.push(trivia),
+            _ => self.leading.push(trivia),
+        }
+        if kind == TriviaKind::Newline || text.contains('\n') {
+            self.line_ended = true;
         }
+    }
+
+    fn error(&mut self, e: ScanError<'a>) {
+        let before = self.handed_out + self.tokens.len();
+        self.errors.push_back((before, e));
+    }
 
-        // consume closing "
+    // consecutive doc comments are joined into one
+    fn push_doc(&mut self, text: Cow<'a, str>) {
+        self.doc = Some(match self.doc.take() {
+            Some(doc) => Cow::Owned(format!("{}\n{}", doc, text)),
+            None => text,
+        });
+    }
+
+    // scans one segment of a string, starting after its opening `"` or after the `}` closing
+    // an interpolation; a segment ending in `${` becomes an INTERPOLATION token
+    fn string(&mut self, opening: Span) {
+        // only allocated once the literal turns out to contain an escape sequence
+        let mut unescaped: Option<String> = None;
+        let interpolation = loop {
+            match self.peek() {
+                None => {
+                    self.error(ScanError::UnterminatedString(opening));
+                    return;
+                }
+                Some('"') => break false,
+                Some('$') if self.peek_next() == Some('{') => break true,
+                Some('\\') => {
+                    let escape = self.cursor_span();
+                    let unescaped = unescaped
+                        .get_or_insert_with(|| self.text(self.start + 1, self.current).to_string());
+                    // will never be None
+                    self.advance();
+                    match self.escape() {
+                        Ok(Some(c)) => unescaped.push(c),
+                        // the string is unterminated, which the next iteration reports
+                        Ok(None) => {}
+                        Err(e) => {
+                            let span = escape.to(self.cursor_span());
+                            self.error(match e {
+                                Some(c) => ScanError::InvalidEscape(c.into(), span),
+                                None => ScanError::InvalidUnicodeEscape(span),
+                            });
+                        }
+                    }
+                }
+                Some(c) => {
+                    if let Some(unescaped) = &mut unescaped {
+                        unescaped.push(c);
+                    }
+                    // will never be None
+                    self.advance();
+                }
+            }
+        };
+
+        let end = self.current;
+        // consume the closing " or the "${"
         // will never be None
         self.advance();
+        if interpolation {
+            self.advance();
+        }
 
-        let literal = take_slice(&self.source, self.start + 1, self.current - 1);
-        self.add_token(TokenType::STRING(literal));
-        Some(())
+        let literal = match unescaped {
+            Some(unescaped) => Cow::Owned(unescaped),
+            None => Cow::Borrowed(self.text(self.start + 1, end)),
+        };
+        if interpolation {
+            self.interpolations.push((0, opening));
+            self.add_token(TokenType::INTERPOLATION(literal));
+        } else {
+            self.add_token(TokenType::STRING(literal));
+        }
+    }
+
+    // decodes the escape sequence following a consumed backslash; an unknown escape is
+    // reported with its character, a malformed \u{...} without one
+    fn escape(&mut self) -> Result<Option<char>, Option<&'a str>> {
+        let position = self.current;
+        let Some(c) = self.advance() else {
+            return Ok(None);
+        };
+        let escaped = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            '0' => '\0',
+            'u' => return self.unicode_escape().map(Some).ok_or(None),
+            _ => return Err(Some(self.text(position, self.current))),
+        };
+        Ok(Some(escaped))
+    }
+
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.advance_if_match('{') {
+            return None;
+        }
+        let digits_start = self.current;
+        self.advance_while(|c| c.is_ascii_hexdigit());
+        let digits = self.text(digits_start, self.current);
+        if !self.advance_if_match('}') || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
     }
 
     fn slash_slash_comment(&mut self) {
-        self.advance_while(|c| c != "\n");
+        // `///` starts a doc comment, `////` and longer are plain comments again
+        let doc = self.peek() == Some('/') && self.peek_next() != Some('/');
+        // the comment runs up to the newline, which is never part of it
+        let rest = &self.source[self.current..];
+        let end = match rest.find('\n') {
+            Some(end) if rest[..end].ends_with('\r') => end - 1,
+            Some(end) => end,
+            None => rest.len(),
+        };
+        self.current += end;
+
+        if doc {
+            let text = self.text(self.start + 3, self.current);
+            let text = text.strip_prefix(' ').unwrap_or(text).trim_end();
+            self.push_doc(Cow::Borrowed(text));
+            self.add_trivia(TriviaKind::DocComment);
+        } else {
+            self.add_trivia(TriviaKind::LineComment);
+        }
     }
 
-    fn slash_star_comment(&mut self) -> Option<()> {
-        while let Some(c) = self.peek() {
-            // having self.peek_next() inside the loop ensures missing closing "/" is caught as error
-            if c == "*" && self.peek_next() == Some("/") {
-                break;
+    // block comments nest, so commenting out code that already has one keeps working
+    fn slash_star_comment(&mut self) {
+        // `/**` starts a doc comment, but `/**/` and `/***` do not
+        let doc = self.peek() == Some('*') && !matches!(self.peek_next(), Some('*' | '/'));
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.peek(), self.peek_next()) {
+                (None, _) => {
+                    let span = self.opening_span(2);
+                    self.error(ScanError::UnterminatedComment(span));
+                    return;
+                }
+                (Some('/'), Some('*')) => depth += 1,
+                (Some('*'), Some('/')) => depth -= 1,
+                _ => {
+                    // will never be None
+                    self.advance();
+                    continue;
+                }
             }
+            // consume the "/*" or "*/"
             // will never be None
             self.advance();
+            self.advance();
         }
 
-        if self.peek() == None {
-            error(self.line, "Unterminated /* */ comment.");
-            return None;
+        if doc {
+            let text = self.text(self.start + 3, self.current - 2);
+            self.push_doc(Cow::Owned(block_doc(text)));
+            self.add_trivia(TriviaKind::DocComment);
+        } else {
+            self.add_trivia(TriviaKind::BlockComment);
         }
-
-        // consume closing "*/"
-        // will never be None
-        self.advance();
-        // will never be None
-        self.advance();
-
-        Some(())
     }
 
+    // the first digit, or the dot of a leading-dot literal, has already been consumed
     fn number(&mut self) {
-        self.advance_while(is_digit);
+        let first = self.source.as_bytes()[self.start];
+        let radix = match (first, self.peek()) {
+            (b'0', Some('x' | 'X')) => 16,
+            (b'0', Some('o' | 'O')) => 8,
+            (b'0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
 
-        // look for a fractional part
-        let next_is_digit = self.peek_next().is_some_and(is_digit);
-        if self.peek() == Some(".") && next_is_digit {
-            // consume the "."
+        let result = if radix == 10 {
+            self.decimal(first != b'.')
+        } else {
+            // consume the prefix
             // will never be None
             self.advance();
-            self.advance_while(is_digit);
+            self.prefixed(radix)
+        };
+
+        // a literal running straight into letters or digits is malformed as a whole
+        let result = result.and_then(|value| {
+            if self.peek().is_some_and(is_alphanumeric) {
+                self.advance_while(is_alphanumeric);
+                Err("unexpected characters after the number")
+            } else {
+                Ok(value)
+            }
+        });
+
+        match result {
+            Ok(value) => self.add_token(TokenType::NUMBER(value)),
+            Err(reason) => {
+                self.advance_while(is_alphanumeric);
+                let literal = self.text(self.start, self.current);
+                let span = self.span();
+                self.error(ScanError::InvalidNumber(literal.into(), reason, span));
+                // a stand-in for the literal, so the parser doesn't report it missing as well
+                self.add_token(TokenType::NUMBER(0.0));
+            }
+        }
+    }
+
+    fn decimal(&mut self, has_integer_part: bool) -> Result<f64, &'static str> {
+        if has_integer_part {
+            self.digits(10, true)?;
+
+            // look for a fractional part
+            let next_is_digit = self.peek_next().is_some_and(is_digit);
+            if self.peek() == Some('.') && next_is_digit {
+                // consume the "."
+                // will never be None
+                self.advance();
+                self.digits(10, false)?;
+            }
+        } else {
+            self.digits(10, false)?;
+        }
+
+        // look for an exponent
+        if self.advance_if_match('e') || self.advance_if_match('E') {
+            if !self.advance_if_match('+') {
+                self.advance_if_match('-');
+            }
+            if !self.digits(10, false)? {
+                return Err("expected digits in the exponent");
+            }
+        }
+
+        let literal = self.text(self.start, self.current);
+        let literal = if literal.contains('_') {
+            Cow::Owned(literal.replace('_', ""))
+        } else {
+            Cow::Borrowed(literal)
+        };
+        literal.parse::<f64>().map_err(|_| "not a valid number")
+    }
+
+    fn prefixed(&mut self, radix: u32) -> Result<f64, &'static str> {
+        let digits_start = self.current;
+        if !self.digits(radix, false)? {
+            return Err(match radix {
+                16 => "expected hexadecimal digits after '0x'",
+                8 => "expected octal digits after '0o'",
+                _ => "expected binary digits after '0b'",
+            });
         }
 
-        let literal = take_slice(&self.source, self.start, self.current)
-            .parse::<f64>()
-            .unwrap();
-        self.add_token(TokenType::NUMBER(literal));
+        let digits = self.text(digits_start, self.current);
+        Ok(digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64))
+    }
+
+    // consumes a run of digits in `radix`, where `_` may only sit between two digits, and
+    // returns whether there were any digits (counting one already consumed as `seen`)
+    fn digits(&mut self, radix: u32, mut seen: bool) -> Result<bool, &'static str> {
+        loop {
+            match self.peek() {
+                Some('_') => {
+                    let next_is_digit = self.peek_next().is_some_and(|c| c.is_digit(radix));
+                    // will never be None
+                    self.advance();
+                    if !seen || !next_is_digit {
+                        return Err("'_' can only separate digits");
+                    }
+                }
+                Some(c) if c.is_digit(radix) => {
+                    seen = true;
+                    // will never be None
+                    self.advance();
+                }
+                _ => return Ok(seen),
+            }
+        }
     }
 
     fn identifier(&mut self) {
+        // most identifiers are ASCII, which can be skipped a byte at a time
+        let bytes = self.source.as_bytes();
+        while bytes
+            .get(self.current)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        {
+            self.current += 1;
+        }
         self.advance_while(is_alphanumeric);
 
-        let text = take_slice(&self.source, self.start, self.current);
-        let token_type = match text {
+        // identifiers end on a grapheme cluster boundary, so a letter carrying a mark that
+        // identifiers cannot contain, like an enclosing circle, is left out as a whole
+        if self.peek().is_some_and(|c| !c.is_ascii()) {
+            let mut cursor = GraphemeCursor::new(self.current, self.source.len(), true);
+            if !cursor.is_boundary(self.source, 0).unwrap_or(true) {
+                let end = cursor
+                    .prev_boundary(self.source, 0)
+                    .ok()
+                    .flatten()
+                    .unwrap_or(self.start);
+                if end <= self.start {
+                    self.current = self.start;
+                    self.unexpected_character();
+                    return;
+                }
+                self.current = end;
+            }
+        }
+
+        let text = self.text(self.start, self.current);
+        // `café` typed with a precomposed or a combining accent is the same name
+        let normalized = if text.is_ascii() { None } else { nfc(text) };
+        let token_type = match normalized.as_deref().unwrap_or(text) {
             "and" => TokenType::AND,
             "class" => TokenType::CLASS,
             "else" => TokenType::ELSE,
@@ -233,21 +961,86 @@ impl<'a> Scanner<'a> {
             _ => TokenType::IDENTIFIER,
         };
         self.add_token(token_type);
+        if let (Some(normalized), Some(token)) = (normalized, self.tokens.back_mut()) {
+            token.extras_mut().normalized = Some(normalized.into_boxed_str());
+        }
     }
 }
 
-fn error(line: usize, message: &str) {
-    eprintln!("[line {}] ScannerError: {}", line, message);
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Result<Token<'a>, ScanError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready() {
+                return Some(item);
+            }
+            if self.finished {
+                return None;
+            }
+            if self.scan_token().is_none() {
+                self.finish();
+            }
+        }
+    }
+}
+
+// most source is ASCII, where every byte is a grapheme cluster of its own
+fn grapheme_count(s: &str) -> usize {
+    if s.is_ascii() {
+        s.len()
+    } else {
+        non_ascii_grapheme_count(s)
+    }
+}
+
+// kept out of line, inlining the segmenter into `mark_start` slows every token down
+#[inline(never)]
+fn non_ascii_grapheme_count(s: &str) -> usize {
+    // nothing below the combining marks at U+0300 joins onto a neighbouring character, which
+    // covers the Latin letters with accents
+    if s.chars().all(|c| c < '\u{300}') {
+        s.chars().count()
+    } else {
+        s.graphemes(true).count()
+    }
+}
+
+// the NFC form of `s` if that differs from `s` itself
+#[cold]
+fn nfc(s: &str) -> Option<String> {
+    match is_nfc_quick(s.chars()) {
+        IsNormalized::Yes => None,
+        _ => Some(s.nfc().collect::<String>()).filter(|nfc| nfc != s),
+    }
+}
+
+fn grapheme_len(s: &str) -> usize {
+    s.graphemes(true).next().map_or(0, str::len)
+}
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
 }
 
-fn is_digit(c: &str) -> bool {
-    c.chars().all(|c| c.is_ascii_digit())
+// strips the indentation and leading `*` that usually decorate the lines of a /** */ comment
+fn block_doc(text: &str) -> String {
+    let lines = text
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect::<Vec<_>>();
+    lines.join("\n").trim_matches('\n').to_string()
 }
 
-fn is_alpha(c: &str) -> bool {
-    c.chars().all(|c| c.is_ascii_alphabetic() || c == '_')
+// identifiers follow UAX #31: an XID_Start character or '_', then XID_Continue characters
+fn is_alpha(c: char) -> bool {
+    unicode_ident::is_xid_start(c) || c == '_'
 }
 
-fn is_alphanumeric(c: &str) -> bool {
-    c.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+fn is_alphanumeric(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
 }
@@ -1,49 +1,164 @@
-use crate::expr::{self, Expr};
+use crate::ast::{ExprId, StmtId};
+use crate::span::Span;
 use crate::token::Token;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone)]
 pub enum Stmt<'a> {
     Block {
-        statements: Vec<Stmt<'a>>,
+        statements: Vec<StmtId>,
+        span: Span,
     },
     Class {
         name: Token<'a>,
-        super_class: expr::Variable<'a>,
-        methods: Vec<Function<'a>>,
+        // a `Variable` expression naming the superclass
+        super_class: Option<ExprId>,
+        // `Function` statements
+        methods: Vec<StmtId>,
+        doc: Option<Cow<'a, str>>,
+        span: Span,
     },
     Expression {
-        expression: Expr<'a>,
-    },
-    Function {
-        name: Token<'a>,
-        params: Vec<Token<'a>>,
-        body: Vec<Stmt<'a>>,
+        expression: ExprId,
+        span: Span,
     },
+    Function(Function<'a>),
     If {
-        condition: Expr<'a>,
-        then_branch: Box<Stmt<'a>>,
-        else_branch: Option<Box<Stmt<'a>>>,
+        condition: ExprId,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+        span: Span,
     },
     Print {
-        expression: Expr<'a>,
+        expression: ExprId,
+        span: Span,
     },
     Return {
         keyword: Token<'a>,
-        value: Option<Expr<'a>>,
+        value: Option<ExprId>,
+        span: Span,
     },
     Var {
         name: Token<'a>,
-        initializer: Option<Expr<'a>>,
+        initializer: Option<ExprId>,
+        doc: Option<Cow<'a, str>>,
+        span: Span,
     },
     While {
-        condition: Expr<'a>,
-        body: Box<Stmt<'a>>,
+        condition: ExprId,
+        body: StmtId,
+        span: Span,
     },
 }
 
+impl<'a> Stmt<'a> {
+    // from the first token of the statement, such as the keyword of a declaration, to its
+    // last, such as the `;` or `}` that ends it
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block { span, .. }
+            | Stmt::Class { span, .. }
+            | Stmt::Expression { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::While { span, .. } => *span,
+            Stmt::Function(function) => function.span,
+        }
+    }
+
+    // the doc comment of a class, function or variable declaration
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Stmt::Class { doc, .. } | Stmt::Var { doc, .. } => doc.as_deref(),
+            Stmt::Function(function) => function.doc.as_deref(),
+            _ => None,
+        }
+    }
+
+    // a copy of the node that no longer borrows the source
+    pub fn into_owned(self) -> Stmt<'static> {
+        match self {
+            Stmt::Block { statements, span } => Stmt::Block { statements, span },
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                doc,
+                span,
+            } => Stmt::Class {
+                name: name.into_owned(),
+                super_class,
+                methods,
+                doc: doc.map(|doc| Cow::Owned(doc.into_owned())),
+                span,
+            },
+            Stmt::Expression { expression, span } => Stmt::Expression { expression, span },
+            Stmt::Function(function) => Stmt::Function(function.into_owned()),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                span,
+            } => Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                span,
+            },
+            Stmt::Print { expression, span } => Stmt::Print { expression, span },
+            Stmt::Return {
+                keyword,
+                value,
+                span,
+            } => Stmt::Return {
+                keyword: keyword.into_owned(),
+                value,
+                span,
+            },
+            Stmt::Var {
+                name,
+                initializer,
+                doc,
+                span,
+            } => Stmt::Var {
+                name: name.into_owned(),
+                initializer,
+                doc: doc.map(|doc| Cow::Owned(doc.into_owned())),
+                span,
+            },
+            Stmt::While {
+                condition,
+                body,
+                span,
+            } => Stmt::While {
+                condition,
+                body,
+                span,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Function<'a> {
     pub name: Token<'a>,
     pub params: Vec<Token<'a>>,
-    pub body: Vec<Stmt<'a>>,
+    pub body: Vec<StmtId>,
+    pub doc: Option<Cow<'a, str>>,
+    // from `fun`, or the name of a method, to the closing `}`
+    pub span: Span,
+}
+
+impl<'a> Function<'a> {
+    pub fn into_owned(self) -> Function<'static> {
+        Function {
+            name: self.name.into_owned(),
+            params: self.params.into_iter().map(Token::into_owned).collect(),
+            body: self.body,
+            doc: self.doc.map(|doc| Cow::Owned(doc.into_owned())),
+            span: self.span,
+        }
+    }
 }
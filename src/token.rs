@@ -1,15 +1,107 @@
+use crate::span::Span;
 use crate::token_type::TokenType;
+use crate::trivia::{Trivia, TriviaList};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token<'a> {
     pub typ: TokenType<'a>,
-    pub lexeme: &'a str,
-    pub line: usize,
+    // borrowed from the source, unless the token was made to outlive it with `into_owned`
+    pub lexeme: Cow<'a, str>,
+    pub span: Span,
+    // most tokens have none of these, so they live out of line to keep tokens small
+    extras: Option<Box<Extras<'a>>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Extras<'a> {
+    // text of the `///` or `/** */` comment directly before the token
+    pub(crate) doc: Option<Cow<'a, str>>,
+    // only filled in by a lossless scan: trailing trivia runs up to and including the end of
+    // the token's line, everything else before a token leads it. Both share one list, the
+    // first `leading` entries leading.
+    pub(crate) trivia: TriviaList<'a>,
+    pub(crate) leading: usize,
+    // NFC form of an identifier whose source text is not normalised already
+    pub(crate) normalized: Option<Box<str>>,
+}
+
+impl<'a> Extras<'a> {
+    fn into_owned(self) -> Extras<'static> {
+        Extras {
+            doc: self.doc.map(|doc| Cow::Owned(doc.into_owned())),
+            trivia: self.trivia.into_owned(),
+            leading: self.leading,
+            normalized: self.normalized,
+        }
+    }
 }
 
 impl<'a> Token<'a> {
-    pub fn new(typ: TokenType<'a>, lexeme: &'a str, line: usize) -> Self {
-        Self { typ, lexeme, line }
+    pub fn new(typ: TokenType<'a>, lexeme: &'a str, span: Span) -> Self {
+        Self {
+            typ,
+            lexeme: Cow::Borrowed(lexeme),
+            span,
+            extras: None,
+        }
+    }
+
+    // a copy of the token that no longer borrows the source
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            typ: self.typ.into_owned(),
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            span: self.span,
+            extras: self.extras.map(|extras| Box::new(extras.into_owned())),
+        }
+    }
+
+    pub(crate) fn extras_mut(&mut self) -> &mut Extras<'a> {
+        self.extras.get_or_insert_with(Default::default)
+    }
+
+    // the name an identifier binds, the same for every spelling that normalises alike
+    pub fn name(&self) -> &str {
+        self.extras
+            .as_ref()
+            .and_then(|extras| extras.normalized.as_deref())
+            .unwrap_or(&self.lexeme)
+    }
+
+    // the `///` or `/** */` comment directly before this token, if any
+    pub fn doc(&self) -> Option<&str> {
+        self.extras.as_ref()?.doc.as_deref()
+    }
+
+    // moves the doc comment out, for the declaration it documents
+    pub(crate) fn take_doc(&mut self) -> Option<Cow<'a, str>> {
+        self.extras.as_mut()?.doc.take()
+    }
+
+    pub fn leading(&self) -> &[Trivia<'a>] {
+        self.extras
+            .as_ref()
+            .map_or(&[], |extras| &extras.trivia.as_slice()[..extras.leading])
+    }
+
+    pub fn trailing(&self) -> &[Trivia<'a>] {
+        self.extras
+            .as_ref()
+            .map_or(&[], |extras| &extras.trivia.as_slice()[extras.leading..])
+    }
+
+    // the token together with its trivia; over a lossless scan these add up to the source
+    pub fn source_text(&self) -> String {
+        let mut text = String::new();
+        for trivia in self.leading() {
+            text.push_str(&trivia.text);
+        }
+        text.push_str(&self.lexeme);
+        for trivia in self.trailing() {
+            text.push_str(&trivia.text);
+        }
+        text
     }
 }
 
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 #[derive(Debug, Clone, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum TokenType<'a> {
@@ -26,7 +28,11 @@ pub enum TokenType<'a> {
 
     // Literals.
     IDENTIFIER,
-    STRING(&'a str),
+    // borrowed straight from the source unless the literal contains escape sequences
+    STRING(Cow<'a, str>),
+    // a string segment ending in `${`, followed by the tokens of the embedded expression;
+    // the template's last segment is a plain STRING
+    INTERPOLATION(Cow<'a, str>),
     NUMBER(f64),
 
     // Keywords.
@@ -52,13 +58,72 @@ pub enum TokenType<'a> {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal<'a> {
-    String(&'a str),
+    String(Cow<'a, str>),
     Number(f64),
     True,
     False,
     Nil,
 }
 
+impl<'a> TokenType<'a> {
+    pub fn into_owned(self) -> TokenType<'static> {
+        match self {
+            TokenType::LEFT_PAREN => TokenType::LEFT_PAREN,
+            TokenType::RIGHT_PAREN => TokenType::RIGHT_PAREN,
+            TokenType::LEFT_BRACE => TokenType::LEFT_BRACE,
+            TokenType::RIGHT_BRACE => TokenType::RIGHT_BRACE,
+            TokenType::COMMA => TokenType::COMMA,
+            TokenType::DOT => TokenType::DOT,
+            TokenType::MINUS => TokenType::MINUS,
+            TokenType::PLUS => TokenType::PLUS,
+            TokenType::SEMICOLON => TokenType::SEMICOLON,
+            TokenType::SLASH => TokenType::SLASH,
+            TokenType::STAR => TokenType::STAR,
+            TokenType::BANG => TokenType::BANG,
+            TokenType::BANG_EQUAL => TokenType::BANG_EQUAL,
+            TokenType::EQUAL => TokenType::EQUAL,
+            TokenType::EQUAL_EQUAL => TokenType::EQUAL_EQUAL,
+            TokenType::GREATER => TokenType::GREATER,
+            TokenType::GREATER_EQUAL => TokenType::GREATER_EQUAL,
+            TokenType::LESS => TokenType::LESS,
+            TokenType::LESS_EQUAL => TokenType::LESS_EQUAL,
+            TokenType::IDENTIFIER => TokenType::IDENTIFIER,
+            TokenType::STRING(s) => TokenType::STRING(Cow::Owned(s.into_owned())),
+            TokenType::INTERPOLATION(s) => TokenType::INTERPOLATION(Cow::Owned(s.into_owned())),
+            TokenType::NUMBER(n) => TokenType::NUMBER(n),
+            TokenType::AND => TokenType::AND,
+            TokenType::CLASS => TokenType::CLASS,
+            TokenType::ELSE => TokenType::ELSE,
+            TokenType::FALSE => TokenType::FALSE,
+            TokenType::FUN => TokenType::FUN,
+            TokenType::FOR => TokenType::FOR,
+            TokenType::IF => TokenType::IF,
+            TokenType::NIL => TokenType::NIL,
+            TokenType::OR => TokenType::OR,
+            TokenType::PRINT => TokenType::PRINT,
+            TokenType::RETURN => TokenType::RETURN,
+            TokenType::SUPER => TokenType::SUPER,
+            TokenType::THIS => TokenType::THIS,
+            TokenType::TRUE => TokenType::TRUE,
+            TokenType::VAR => TokenType::VAR,
+            TokenType::WHILE => TokenType::WHILE,
+            TokenType::EOF => TokenType::EOF,
+        }
+    }
+}
+
+impl<'a> Literal<'a> {
+    pub fn into_owned(self) -> Literal<'static> {
+        match self {
+            Literal::String(s) => Literal::String(Cow::Owned(s.into_owned())),
+            Literal::Number(n) => Literal::Number(n),
+            Literal::True => Literal::True,
+            Literal::False => Literal::False,
+            Literal::Nil => Literal::Nil,
+        }
+    }
+}
+
 impl<'a> From<Literal<'a>> for TokenType<'a> {
     fn from(literal: Literal<'a>) -> Self {
         match literal {
@@ -0,0 +1,98 @@
+use crate::span::Span;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    // spaces, tabs and lone carriage returns
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    // `///` and `/** */` comments, whose text is also attached to the following token
+    DocComment,
+    // source the scanner could not make a token of, kept so that nothing is lost
+    Skipped,
+}
+
+// source text between tokens, only kept when the scanner runs in lossless mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: Cow<'a, str>,
+    pub span: Span,
+}
+
+impl<'a> Trivia<'a> {
+    pub fn new(kind: TriviaKind, text: &'a str, span: Span) -> Self {
+        Self {
+            kind,
+            text: Cow::Borrowed(text),
+            span,
+        }
+    }
+
+    pub fn into_owned(self) -> Trivia<'static> {
+        Trivia {
+            kind: self.kind,
+            text: Cow::Owned(self.text.into_owned()),
+            span: self.span,
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for Trivia<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// the trivia of one token. Most tokens have a single space or newline around them, which
+// is kept in place rather than in a list of its own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) enum TriviaList<'a> {
+    #[default]
+    Empty,
+    One(Trivia<'a>),
+    Many(Vec<Trivia<'a>>),
+}
+
+impl<'a> TriviaList<'a> {
+    pub(crate) fn as_slice(&self) -> &[Trivia<'a>] {
+        match self {
+            TriviaList::Empty => &[],
+            TriviaList::One(trivia) => std::slice::from_ref(trivia),
+            TriviaList::Many(list) => list,
+        }
+    }
+
+    pub(crate) fn push(&mut self, trivia: Trivia<'a>) {
+        *self = match std::mem::take(self) {
+            TriviaList::Empty => TriviaList::One(trivia),
+            TriviaList::One(first) => TriviaList::Many(vec![first, trivia]),
+            TriviaList::Many(mut list) => {
+                list.push(trivia);
+                TriviaList::Many(list)
+            }
+        }
+    }
+
+    pub(crate) fn into_owned(self) -> TriviaList<'static> {
+        match self {
+            TriviaList::Empty => TriviaList::Empty,
+            TriviaList::One(trivia) => TriviaList::One(trivia.into_owned()),
+            TriviaList::Many(list) => {
+                TriviaList::Many(list.into_iter().map(Trivia::into_owned).collect())
+            }
+        }
+    }
+}
+
+impl<'a> FromIterator<Trivia<'a>> for TriviaList<'a> {
+    fn from_iter<I: IntoIterator<Item = Trivia<'a>>>(iter: I) -> Self {
+        let mut list = TriviaList::Empty;
+        for trivia in iter {
+            list.push(trivia);
+        }
+        list
+    }
+}
@@ -0,0 +1,384 @@
+use crate::ast::{Ast, ExprId, StmtId};
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+
+// a read-only pass over expressions, producing an `R` for each one it visits, such as the
+// `String` a printer makes of it. A pass that produces nothing and only cares about some nodes
+// is easier written as a `Walker`.
+// `'t` borrows the tree, so that a pass can keep references to its nodes.
+pub trait ExprVisitor<'t, 'a: 't, R = ()> {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) -> R;
+}
+
+// a read-only pass over statements, producing an `R` for each one it visits
+pub trait StmtVisitor<'t, 'a: 't, R = ()> {
+    fn visit_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) -> R;
+
+    // visits the top-level statements of a program in order
+    fn visit_program(&mut self, ast: &'t Ast<'a>) -> Vec<R> {
+        ast.statements()
+            .iter()
+            .map(|&statement| self.visit_stmt(ast, statement))
+            .collect()
+    }
+}
+
+// visits the children of an expression in source order
+pub fn walk_expr<'t, 'a: 't, V: ExprVisitor<'t, 'a> + ?Sized>(
+    visitor: &mut V,
+    ast: &'t Ast<'a>,
+    id: ExprId,
+) {
+    match &ast[id] {
+        Expr::Assign { value, .. } => visitor.visit_expr(ast, *value),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            visitor.visit_expr(ast, *left);
+            visitor.visit_expr(ast, *right);
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expr(ast, *callee);
+            for &argument in arguments {
+                visitor.visit_expr(ast, argument);
+            }
+        }
+        Expr::Get { object, .. } => visitor.visit_expr(ast, *object),
+        Expr::Grouping { expression, .. } => visitor.visit_expr(ast, *expression),
+        Expr::Interpolation { parts, .. } => {
+            for &part in parts {
+                visitor.visit_expr(ast, part);
+            }
+        }
+        Expr::Set { object, value, .. } => {
+            visitor.visit_expr(ast, *object);
+            visitor.visit_expr(ast, *value);
+        }
+        Expr::Unary { right, .. } => visitor.visit_expr(ast, *right),
+        Expr::Literal { .. } | Expr::Super { .. } | Expr::This { .. } | Expr::Variable { .. } => {}
+    }
+}
+
+// visits the child statements and expressions of a statement in source order
+pub fn walk_stmt<'t, 'a: 't, V: StmtVisitor<'t, 'a> + ExprVisitor<'t, 'a> + ?Sized>(
+    visitor: &mut V,
+    ast: &'t Ast<'a>,
+    id: StmtId,
+) {
+    match &ast[id] {
+        Stmt::Block { statements, .. } => {
+            for &statement in statements {
+                visitor.visit_stmt(ast, statement);
+            }
+        }
+        Stmt::Class {
+            super_class,
+            methods,
+            ..
+        } => {
+            if let Some(super_class) = *super_class {
+                visitor.visit_expr(ast, super_class);
+            }
+            for &method in methods {
+                visitor.visit_stmt(ast, method);
+            }
+        }
+        Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+            visitor.visit_expr(ast, *expression)
+        }
+        Stmt::Function(function) => {
+            for &statement in &function.body {
+                visitor.visit_stmt(ast, statement);
+            }
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            visitor.visit_expr(ast, *condition);
+            visitor.visit_stmt(ast, *then_branch);
+            if let Some(else_branch) = *else_branch {
+                visitor.visit_stmt(ast, else_branch);
+            }
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = *value {
+                visitor.visit_expr(ast, value);
+            }
+        }
+        Stmt::Var { initializer, .. } => {
+            if let Some(initializer) = *initializer {
+                visitor.visit_expr(ast, initializer);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            visitor.visit_expr(ast, *condition);
+            visitor.visit_stmt(ast, *body);
+        }
+    }
+}
+
+// a read-only pass that produces nothing and only cares about some nodes, such as a lint.
+// Every kind of node has a hook that by default walks on into its children, so a pass
+// overrides the hooks for the nodes it cares about, calling `walk_expr` or `walk_stmt` from
+// them to keep going below. Every walker is an `ExprVisitor` and a `StmtVisitor`.
+pub trait Walker<'t, 'a: 't> {
+    fn visit_assign(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_binary(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_call(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_get(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_grouping(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_interpolation(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_literal(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_logical(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_set(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_super(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_this(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_unary(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_variable(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    fn visit_block_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_class_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_function_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_if_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_print_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_return_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_var_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_while_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+}
+
+impl<'t, 'a: 't, W: Walker<'t, 'a> + ?Sized> ExprVisitor<'t, 'a> for W {
+    fn visit_expr(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        match &ast[id] {
+            Expr::Assign { .. } => self.visit_assign(ast, id),
+            Expr::Binary { .. } => self.visit_binary(ast, id),
+            Expr::Call { .. } => self.visit_call(ast, id),
+            Expr::Get { .. } => self.visit_get(ast, id),
+            Expr::Grouping { .. } => self.visit_grouping(ast, id),
+            Expr::Interpolation { .. } => self.visit_interpolation(ast, id),
+            Expr::Literal { .. } => self.visit_literal(ast, id),
+            Expr::Logical { .. } => self.visit_logical(ast, id),
+            Expr::Set { .. } => self.visit_set(ast, id),
+            Expr::Super { .. } => self.visit_super(ast, id),
+            Expr::This { .. } => self.visit_this(ast, id),
+            Expr::Unary { .. } => self.visit_unary(ast, id),
+            Expr::Variable { .. } => self.visit_variable(ast, id),
+        }
+    }
+}
+
+impl<'t, 'a: 't, W: Walker<'t, 'a> + ?Sized> StmtVisitor<'t, 'a> for W {
+    fn visit_stmt(&mut self, ast: &'t Ast<'a>, id: StmtId) {
+        match &ast[id] {
+            Stmt::Block { .. } => self.visit_block_stmt(ast, id),
+            Stmt::Class { .. } => self.visit_class_stmt(ast, id),
+            Stmt::Expression { .. } => self.visit_expression_stmt(ast, id),
+            Stmt::Function(_) => self.visit_function_stmt(ast, id),
+            Stmt::If { .. } => self.visit_if_stmt(ast, id),
+            Stmt::Print { .. } => self.visit_print_stmt(ast, id),
+            Stmt::Return { .. } => self.visit_return_stmt(ast, id),
+            Stmt::Var { .. } => self.visit_var_stmt(ast, id),
+            Stmt::While { .. } => self.visit_while_stmt(ast, id),
+        }
+    }
+}
+
+// a pass that rebuilds the tree. Each `fold_*` returns the id of the node to put in place of
+// the one it was given: the same id when the node was kept or changed in place, or the id of
+// a node it added. The defaults fold the children first, so a folder sees them already
+// rewritten.
+pub trait Folder<'a> {
+    fn fold_expr(&mut self, ast: &mut Ast<'a>, id: ExprId) -> ExprId {
+        fold_expr_children(self, ast, id);
+        id
+    }
+
+    fn fold_stmt(&mut self, ast: &mut Ast<'a>, id: StmtId) -> StmtId {
+        fold_stmt_children(self, ast, id);
+        id
+    }
+
+    // folds the top-level statements of a program in order
+    fn fold_program(&mut self, ast: &mut Ast<'a>) {
+        let mut statements = ast.statements().to_vec();
+        for statement in &mut statements {
+            *statement = self.fold_stmt(ast, *statement);
+        }
+        ast.set_statements(statements);
+    }
+}
+
+// folds the children of an expression and points it at their replacements. The node is
+// copied out while that happens, since folding a child may add nodes to the tree.
+pub fn fold_expr_children<'a, F: Folder<'a> + ?Sized>(
+    folder: &mut F,
+    ast: &mut Ast<'a>,
+    id: ExprId,
+) {
+    let mut expr = ast[id].clone();
+    match &mut expr {
+        Expr::Assign { value, .. } => *value = folder.fold_expr(ast, *value),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            *left = folder.fold_expr(ast, *left);
+            *right = folder.fold_expr(ast, *right);
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            *callee = folder.fold_expr(ast, *callee);
+            for argument in arguments {
+                *argument = folder.fold_expr(ast, *argument);
+            }
+        }
+        Expr::Get { object, .. } => *object = folder.fold_expr(ast, *object),
+        Expr::Grouping { expression, .. } => *expression = folder.fold_expr(ast, *expression),
+        Expr::Interpolation { parts, .. } => {
+            for part in parts {
+                *part = folder.fold_expr(ast, *part);
+            }
+        }
+        Expr::Set { object, value, .. } => {
+            *object = folder.fold_expr(ast, *object);
+            *value = folder.fold_expr(ast, *value);
+        }
+        Expr::Unary { right, .. } => *right = folder.fold_expr(ast, *right),
+        Expr::Literal { .. } | Expr::Super { .. } | Expr::This { .. } | Expr::Variable { .. } => {
+            return
+        }
+    }
+    *ast.expr_mut(id) = expr;
+}
+
+// folds the children of a statement and points it at their replacements
+pub fn fold_stmt_children<'a, F: Folder<'a> + ?Sized>(
+    folder: &mut F,
+    ast: &mut Ast<'a>,
+    id: StmtId,
+) {
+    let mut stmt = ast[id].clone();
+    match &mut stmt {
+        Stmt::Block { statements, .. } => {
+            for statement in statements {
+                *statement = folder.fold_stmt(ast, *statement);
+            }
+        }
+        Stmt::Class {
+            super_class,
+            methods,
+            ..
+        } => {
+            if let Some(super_class) = super_class {
+                *super_class = folder.fold_expr(ast, *super_class);
+            }
+            for method in methods {
+                *method = folder.fold_stmt(ast, *method);
+            }
+        }
+        Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+            *expression = folder.fold_expr(ast, *expression)
+        }
+        Stmt::Function(function) => {
+            for statement in &mut function.body {
+                *statement = folder.fold_stmt(ast, *statement);
+            }
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            *condition = folder.fold_expr(ast, *condition);
+            *then_branch = folder.fold_stmt(ast, *then_branch);
+            if let Some(else_branch) = else_branch {
+                *else_branch = folder.fold_stmt(ast, *else_branch);
+            }
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                *value = folder.fold_expr(ast, *value);
+            }
+        }
+        Stmt::Var { initializer, .. } => {
+            if let Some(initializer) = initializer {
+                *initializer = folder.fold_expr(ast, *initializer);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            *condition = folder.fold_expr(ast, *condition);
+            *body = folder.fold_stmt(ast, *body);
+        }
+    }
+    *ast.stmt_mut(id) = stmt;
+}
@@ -0,0 +1,44 @@
+use rlox::dump;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use serde_json::Value;
+
+fn json(source: &str) -> Value {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    dump::to_json(&ast)
+}
+
+// the source text a node's span covers
+fn spanned<'s>(source: &'s str, node: &Value) -> &'s str {
+    let start = node["span"]["start"].as_u64().unwrap() as usize;
+    let end = node["span"]["end"].as_u64().unwrap() as usize;
+    &source[start..end]
+}
+
+#[test]
+fn statements_span_their_whole_text() {
+    let source =
+        "class A {\n    m() {\n        return 1;\n    }\n}\nfun f(a) { a; }\nvar x = 1;\nvar y;\n";
+    let dumped = json(source);
+    let statements = dumped["statements"].as_array().unwrap();
+    assert_eq!(
+        spanned(source, &statements[0]),
+        "class A {\n    m() {\n        return 1;\n    }\n}"
+    );
+    let method = &statements[0]["methods"][0];
+    assert_eq!(spanned(source, method), "m() {\n        return 1;\n    }");
+    assert_eq!(spanned(source, &method["body"][0]), "return 1;");
+    assert_eq!(spanned(source, &statements[1]), "fun f(a) { a; }");
+    assert_eq!(spanned(source, &statements[1]["body"][0]), "a;");
+    assert_eq!(spanned(source, &statements[2]), "var x = 1;");
+    assert_eq!(spanned(source, &statements[3]), "var y;");
+}
+
+#[test]
+fn infinite_number_literals_are_strings() {
+    let dumped = json("print 1e400;");
+    let literal = &dumped["statements"][0]["expression"];
+    assert_eq!(literal["type"], "number");
+    assert_eq!(literal["value"], "inf");
+}
@@ -0,0 +1,71 @@
+use rlox::dump;
+use rlox::formatter::{format_source, BraceStyle, FormatConfig};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+
+// the program a source parses to, leaving out comments and layout
+fn meaning(source: &str) -> String {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    dump::to_sexpr(&ast)
+}
+
+#[rstest]
+#[case::numbers("print 1e400 > 1;\nprint 0x1F + 0b101 + 0o17 + 1_000.5e-1 + 1.5e-3 + 1e21;\n")]
+#[case::strings("print \"tab\\there \\\"quoted\\\" \\u{e9}\";\nprint \"${1 + 2} is \\${not} ${\"nested ${3}\"}\";\n")]
+#[case::comments("// leading\nvar a = 1; // trailing\n\n\n/* block */ print a;\n/// doc\nfun f() {\n  // inside\n}\n")]
+#[case::control_flow(
+    "if (a) print 1; else if (b) { print 2; } else print 3;\nwhile (a and !b or c) a = a - 1;\n"
+)]
+#[case::for_loops("for (;;) print 1;\nfor (var i = 0; i < 3; i = i + 1) { print i; }\nfor (i = 0; i < 3;) print i;\n")]
+#[case::classes("class A < B { init(x) { this.x = x; } get() { return super.get() + this.x; } }\nprint A(1).get();\n")]
+#[case::inline_comments(
+    "fun f(a, // first\n  b /* last */) {\n  return g(a, /* x */ b) + // sum\n    c;\n}\n"
+)]
+#[case::long_chains("var total = alpha + beta + gamma + delta + epsilon + zeta + eta + theta + iota + kappa + lambda + mu + nu;\nif (first and second or third and fourth or fifth and sixth or seventh and eighth and ninth) print 1;\n")]
+#[case::long_lines("fun withManyParameters(first, second, third, fourth, fifth, sixth, seventh, eighth) { return first; }\nprint withManyParameters(\"one argument\", \"two arguments\", \"three arguments\", \"four arguments\");\n")]
+fn formatting_keeps_meaning_and_is_idempotent(
+    #[case] source: &str,
+    #[values(BraceStyle::SameLine, BraceStyle::NextLine)] brace_style: BraceStyle,
+) {
+    let config = FormatConfig::default().with_brace_style(brace_style);
+    let formatted = format_source(source, &config).unwrap();
+    assert_eq!(meaning(&formatted), meaning(source), "{}", formatted);
+    assert_eq!(format_source(&formatted, &config).unwrap(), formatted);
+}
+
+#[test]
+fn number_literals_keep_their_spelling() {
+    let formatted = format_source("print   0x1F+1e400;", &FormatConfig::default()).unwrap();
+    assert_eq!(formatted, "print 0x1F + 1e400;\n");
+}
+
+#[test]
+fn long_chains_of_operators_are_split() {
+    let source = "var total = alpha + beta + gamma * delta - epsilon + zeta;\nwhile (first and second or third) go();\n";
+    let config = FormatConfig::default().with_max_width(30);
+    assert_eq!(
+        format_source(source, &config).unwrap(),
+        "var total = alpha +\n    beta +\n    gamma * delta -\n    epsilon +\n    zeta;\nwhile (first and second or\n    third)\n    go();\n"
+    );
+}
+
+#[rstest]
+#[case::between_parameters("fun m(a, /* p */ b) {}\n", "fun m(a, /* p */ b) {}\n")]
+#[case::between_arguments("print f(1 /* one */, 2);\n", "print f(1, /* one */ 2);\n")]
+#[case::after_an_argument("print f(1, // arg\n2);\n", "print f(\n    1, // arg\n    2\n);\n")]
+#[case::before_an_argument(
+    "print f(1,\n// two\n2);\n",
+    "print f(\n    1,\n    // two\n    2\n);\n"
+)]
+#[case::around_an_operator("print a /* a */ + /* b */ b;\n", "print a /* a */ + /* b */ b;\n")]
+#[case::after_an_operator("print a + // a\nb;\n", "print a + // a\n    b;\n")]
+#[case::after_a_header("while (a) /* w */ a = a - 1;\n", "while (a) /* w */ a = a - 1;\n")]
+#[case::line_after_a_header("while (a) // w\na = a - 1;\n", "while (a) // w\n    a = a - 1;\n")]
+#[case::before_a_brace("class A /* c */ {}\n", "class A /* c */ {}\n")]
+fn comments_stay_next_to_what_they_followed(#[case] source: &str, #[case] formatted: &str) {
+    let config = FormatConfig::default();
+    assert_eq!(format_source(source, &config).unwrap(), formatted);
+    assert_eq!(format_source(formatted, &config).unwrap(), formatted);
+}
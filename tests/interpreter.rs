@@ -0,0 +1,261 @@
+use rlox::interpreter::{Interpreter, Program, RuntimeError, DEFAULT_MAX_CALL_DEPTH};
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+use std::rc::Rc;
+
+// runs `source` and returns what it printed and how it ended
+fn run(source: &str) -> (String, Result<(), RuntimeError>) {
+    run_limited(source, DEFAULT_MAX_CALL_DEPTH)
+}
+
+fn run_limited(source: &str, max_call_depth: usize) -> (String, Result<(), RuntimeError>) {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let locals = Resolver::new(&ast).resolve().unwrap();
+    let mut output = Vec::new();
+    let result = Interpreter::new()
+        .with_output(Box::new(&mut output))
+        .with_max_call_depth(max_call_depth)
+        .interpret(Rc::new(Program::new(ast, locals)));
+    (String::from_utf8(output).unwrap(), result)
+}
+
+#[rstest]
+#[case::numbers(
+    "print 1; print 2.5; print -0.5 * 4; print 1 / 0;",
+    "1\n2.5\n-2\ninf\n"
+)]
+#[case::strings("print \"a\" + \"b\"; print \"${1 + 1} apples\";", "ab\n2 apples\n")]
+#[case::nested_templates(
+    "var n = 2; print \"${n} ${\"x${n * 2}y\"} ${nil} ${true}\";",
+    "2 x4y nil true\n"
+)]
+#[case::normalized_names("var caf\u{e9} = 1; print cafe\u{301};", "1\n")]
+#[case::literals("print nil; print true; print nil == false;", "nil\ntrue\nfalse\n")]
+#[case::callables("fun f() {} print f; print clock;", "<fn f>\n<native fn>\n")]
+#[case::classes("class A {} print A; print A();", "A\nA instance\n")]
+fn print_writes_to_the_output(#[case] source: &str, #[case] printed: &str) {
+    let (output, result) = run(source);
+    result.unwrap();
+    assert_eq!(output, printed);
+}
+
+#[test]
+fn output_before_a_runtime_error_is_kept() {
+    let (output, result) = run("print 1; print nil + 1; print 2;");
+    assert_eq!(output, "1\n");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Operands must be two numbers or two strings at line 1, column 20!"
+    );
+}
+
+// what the REPL does with each line: a bare expression has its value echoed to the output,
+// anything else runs as a program, and errors are rendered to the diagnostics
+fn repl(lines: &[&'static str]) -> (String, String) {
+    let mut output = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut interpreter = Interpreter::new()
+        .with_output(Box::new(&mut output))
+        .with_diagnostics(Box::new(&mut diagnostics));
+    for line in lines {
+        let tokens = Scanner::new(line).scan_tokens().unwrap();
+        let result = match Parser::new(tokens.iter().cloned()).parse() {
+            Ok((ast, expression)) => {
+                let locals = Resolver::new(&ast).resolve_expression(expression).unwrap();
+                let program = Rc::new(Program::new(ast, locals));
+                interpreter
+                    .evaluate(program, expression)
+                    .map(|value| writeln!(interpreter.output(), "{}", value).unwrap())
+            }
+            Err(_) => {
+                let ast = Parser::new(tokens).parse_program().unwrap();
+                let locals = Resolver::new(&ast).resolve().unwrap();
+                interpreter.interpret(Rc::new(Program::new(ast, locals)))
+            }
+        };
+        if let Err(e) = result {
+            interpreter.report(&e, "<repl>", line);
+        }
+    }
+    drop(interpreter);
+    (
+        String::from_utf8(output).unwrap(),
+        String::from_utf8(diagnostics).unwrap(),
+    )
+}
+
+#[test]
+fn repl_echoes_and_errors_go_to_their_own_sinks() {
+    let (output, diagnostics) = repl(&[
+        "fun twice(x) { return x * 2; }",
+        "twice(21)",
+        "print \"printed\";",
+        "\"a\" - 1",
+        "twice",
+    ]);
+    assert_eq!(output, "42\nprinted\n<fn twice>\n");
+    assert!(
+        diagnostics.starts_with("error[E401]: Operands must be numbers"),
+        "{}",
+        diagnostics
+    );
+    assert!(diagnostics.contains("\"a\" - 1"), "{}", diagnostics);
+}
+
+#[test]
+fn runtime_errors_are_reported_to_the_diagnostics() {
+    let source = "fun f(x) {\n  return -x;\n}\nprint f(\"a\");";
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let locals = Resolver::new(&ast).resolve().unwrap();
+    let mut diagnostics = Vec::new();
+    let mut interpreter = Interpreter::new().with_diagnostics(Box::new(&mut diagnostics));
+    let e = interpreter
+        .interpret(Rc::new(Program::new(ast, locals)))
+        .unwrap_err();
+    interpreter.report(&e, "script.lox", source);
+    drop(interpreter);
+    assert_eq!(
+        String::from_utf8(diagnostics).unwrap(),
+        "error[E401]: Operand must be a number\n \
+         --> script.lox:2:10\n  \
+         |\n\
+         2 |   return -x;\n  \
+         |          ^\n\
+         [line 2] in f()\n\
+         [line 4] in script\n"
+    );
+}
+
+#[test]
+fn runaway_recursion_is_a_runtime_error() {
+    // the test harness runs tests on small stacks
+    let (output, result) = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| run_limited("print 1;\nfun f(n) { return f(n + 1); }\nf(0);", 256))
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(output, "1\n");
+    let e = result.unwrap_err();
+    assert_eq!(e.to_string(), "Stack overflow at line 2, column 26!");
+    assert_eq!(e.stack_trace().len(), 257);
+    assert_eq!(e.stack_trace().last().unwrap(), "[line 3] in script");
+}
+
+#[test]
+fn recursion_up_to_the_limit_runs() {
+    let (output, result) = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| {
+            run_limited(
+                "fun f(n) { if (n == 256) return n; return f(n + 1); }\nprint f(1);",
+                256,
+            )
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    result.unwrap();
+    assert_eq!(output, "256\n");
+}
+
+#[test]
+fn recursion_a_thousand_calls_deep_runs() {
+    let source = "fun sum(n) { if (n == 0) return 0; return n + sum(n - 1); }\nprint sum(1000);";
+    let (output, result) = std::thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(|| run(source))
+        .unwrap()
+        .join()
+        .unwrap();
+    result.unwrap();
+    assert_eq!(output, "500500\n");
+}
+
+#[test]
+fn top_level_return_stops_the_program() {
+    // the resolver rejects a top-level `return`, so the program is run without it
+    let tokens = Scanner::new("print 1; return; print 2;")
+        .scan_tokens()
+        .unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let mut output = Vec::new();
+    Interpreter::new()
+        .with_output(Box::new(&mut output))
+        .interpret(Rc::new(Program::new(ast, Default::default())))
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+}
+
+#[rstest]
+#[case::nil("nil", "false")]
+#[case::false_itself("false", "false")]
+#[case::true_itself("true", "true")]
+#[case::zero("0", "true")]
+#[case::empty_string("\"\"", "true")]
+#[case::function("clock", "true")]
+#[case::instance("A()", "true")]
+fn only_nil_and_false_are_falsey(#[case] value: &str, #[case] truthy: &str) {
+    let source = format!(
+        "class A {{}} print !!{}; if ({}) print 1; else print 0;",
+        value, value
+    );
+    let (output, result) = run(&source);
+    result.unwrap();
+    let taken = if truthy == "true" { "1" } else { "0" };
+    assert_eq!(output, format!("{}\n{}\n", truthy, taken));
+}
+
+#[rstest]
+#[case::or_takes_a_truthy_left("print 1 or 2;", "1\n")]
+#[case::or_falls_through("print nil or \"b\";", "b\n")]
+#[case::or_of_two_falsey("print false or nil;", "nil\n")]
+#[case::and_stops_at_falsey("print nil and 2;", "nil\n")]
+#[case::and_takes_the_right("print 1 and \"b\";", "b\n")]
+#[case::and_binds_tighter("print false and 1 or 2;", "2\n")]
+fn logical_operators_return_the_deciding_operand(#[case] source: &str, #[case] printed: &str) {
+    let (output, result) = run(source);
+    result.unwrap();
+    assert_eq!(output, printed);
+}
+
+#[rstest]
+#[case::or("true or f();")]
+#[case::and("false and f();")]
+#[case::chained("nil and f() and f();")]
+fn logical_operators_short_circuit(#[case] expression: &str) {
+    let source = format!(
+        "var calls = 0; fun f() {{ calls = calls + 1; return true; }} {} print calls;",
+        expression
+    );
+    let (output, result) = run(&source);
+    result.unwrap();
+    assert_eq!(output, "0\n");
+}
+
+#[rstest]
+#[case::nil_and_false("nil == false", "false")]
+#[case::nil_and_nil("nil == nil", "true")]
+#[case::number_and_string("1 == \"1\"", "false")]
+#[case::zero_and_false("0 == false", "false")]
+#[case::equal_strings("\"a\" + \"b\" == \"ab\"", "true")]
+#[case::not_equal_numbers("1 != 2", "true")]
+#[case::nan("0 / 0 == 0 / 0", "false")]
+#[case::same_instance("a == a", "true")]
+#[case::distinct_instances("a == A()", "false")]
+#[case::same_class("A == A", "true")]
+#[case::same_function("f == f", "true")]
+#[case::bound_methods("a.m == a.m", "false")]
+fn equality_works_across_types(#[case] expression: &str, #[case] equal: &str) {
+    let source = format!(
+        "class A {{ m() {{}} }} var a = A(); fun f() {{}} print {};",
+        expression
+    );
+    let (output, result) = run(&source);
+    result.unwrap();
+    assert_eq!(output, format!("{}\n", equal));
+}
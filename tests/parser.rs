@@ -0,0 +1,105 @@
+use rlox::ast::Ast;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::stmt::Stmt;
+use rstest::rstest;
+
+fn parse(source: &str) -> Ast<'_> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    Parser::new(tokens).parse_program().unwrap()
+}
+
+// the messages of the errors parsing `source` gives, each with the line it was found on
+fn errors(source: &str) -> Vec<String> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let errors = Parser::new(tokens).parse_program().unwrap_err();
+    errors
+        .iter()
+        .map(|e| {
+            let diagnostic = e.to_diagnostic();
+            let line = diagnostic.primary.map_or(0, |label| label.span.line);
+            format!("{}: {}", line, diagnostic.message)
+        })
+        .collect()
+}
+
+#[rstest]
+#[case::string_after_expression(
+    "print \"${a \"b\"}\";",
+    "1: Expected '}' after interpolated expression, but got \"b\""
+)]
+#[case::two_expressions(
+    "print \"${1 2}\";",
+    "1: Expected '}' after interpolated expression, but got 2"
+)]
+#[case::empty_interpolation("print \"${}\";", "1: Expected expression, but got }\"")]
+fn malformed_interpolations_are_errors(#[case] source: &str, #[case] error: &str) {
+    assert_eq!(errors(source), vec![error]);
+}
+
+#[rstest]
+#[case::line_docs("/// Adds one.\n/// Or two.\nfun f() {}", Some("Adds one.\nOr two."))]
+#[case::block_doc(
+    "/**\n * A point.\n *\n * In 2D.\n */\nclass P {}",
+    Some("A point.\n\nIn 2D.")
+)]
+#[case::variable("/// The answer.\nvar x = 42;", Some("The answer."))]
+#[case::four_slashes("//// not a doc\nvar x;", None)]
+#[case::empty_block("/**/ var x;", None)]
+#[case::three_stars("/*** not a doc */ var x;", None)]
+#[case::plain_comments("// a\n/* b */ var x;", None)]
+fn doc_comments_attach_to_the_next_declaration(#[case] source: &str, #[case] doc: Option<&str>) {
+    let ast = parse(source);
+    assert_eq!(ast[ast.statements()[0]].doc(), doc);
+}
+
+#[test]
+fn doc_comments_attach_to_methods() {
+    let ast = parse("/// A.\nclass A {\n  /// Makes one.\n  init() {}\n  m() {}\n}");
+    let class = ast.statements()[0];
+    assert_eq!(ast[class].doc(), Some("A."));
+    let Stmt::Class { methods, .. } = &ast[class] else {
+        panic!("not a class");
+    };
+    let docs = methods
+        .iter()
+        .map(|&method| ast[method].doc())
+        .collect::<Vec<_>>();
+    assert_eq!(docs, vec![Some("Makes one."), None]);
+}
+
+#[test]
+fn a_doc_comment_before_a_statement_is_dropped() {
+    let ast = parse("/// stray\nprint 1;\nvar x;");
+    assert_eq!(ast[ast.statements()[1]].doc(), None);
+}
+
+#[test]
+fn parsing_recovers_to_report_every_syntax_error() {
+    let source =
+        "var = 1;\nprint 1;\nprint (2;\nvar b = 3;\n{ print 4; 5 = 6; }\nfun f( {}\nprint 7;\n";
+    assert_eq!(
+        errors(source),
+        vec![
+            "1: Expected variable name, but got =",
+            "3: Expected ')' after expression, but got ;",
+            "5: Invalid assignment target",
+            "6: Expected parameter name, but got {",
+        ]
+    );
+
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let (ast, _) = Parser::new(tokens).parse_partial();
+    let parsed = ast
+        .statements()
+        .iter()
+        .map(|&statement| {
+            let span = ast.stmt_span(statement);
+            &source[span.start..span.end]
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        parsed,
+        vec!["print 1;", "var b = 3;", "{ print 4; 5 = 6; }", "print 7;"]
+    );
+}
@@ -0,0 +1,93 @@
+use rlox::read_scanner::ReadScanner;
+use rlox::scanner::{ScanError, Scanner};
+use rlox::token::Token;
+use rstest::rstest;
+use std::io::{self, Read};
+
+// hands out at most `size` bytes per read, so chunks end at awkward places
+struct Trickle<'s> {
+    source: &'s [u8],
+    size: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.size.min(buf.len()).min(self.source.len());
+        buf[..n].copy_from_slice(&self.source[..n]);
+        self.source = &self.source[n..];
+        Ok(n)
+    }
+}
+
+type Scanned = Vec<Result<Token<'static>, ScanError<'static>>>;
+
+fn in_memory(source: &str, lossless: bool) -> Scanned {
+    let scanner = Scanner::new(source);
+    let scanner = if lossless {
+        scanner.with_trivia()
+    } else {
+        scanner
+    };
+    scanner
+        .map(|item| item.map(Token::into_owned).map_err(ScanError::into_owned))
+        .collect()
+}
+
+fn chunked(source: &str, size: usize, lossless: bool) -> Scanned {
+    let reader = Trickle {
+        source: source.as_bytes(),
+        size,
+    };
+    let scanner = ReadScanner::new(reader);
+    let mut scanner = if lossless {
+        scanner.with_trivia()
+    } else {
+        scanner
+    };
+    let scanned = scanner.by_ref().collect();
+    assert!(scanner.take_error().is_none());
+    scanned
+}
+
+fn long_line() -> String {
+    let terms = (0..12_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    format!("var café = 1;\nprint {};\nprint café;\n", terms.join(" + "))
+}
+
+fn long_string() -> String {
+    format!("print \"{}\";\nprint 1;\n", "é".repeat(70_000))
+}
+
+#[rstest]
+#[case::long_line(long_line())]
+#[case::long_string(long_string())]
+#[case::crlf("var a = 1;\r\n// comment\r\nprint a\r\n  + 2;\r\n\r\n".to_string())]
+#[case::multi_line_string("print \"one\ntwo\r\nthree\";\nprint 4;\n".to_string())]
+#[case::multi_line_comment("/* one\n/* nested\n */ two\n*/ print 1; /// doc\nfun f() {}\n".to_string())]
+#[case::multi_line_template("print \"a ${\n  1 +\n  \"b ${ 2\n}\"\n} c\";\nprint 3;\n".to_string())]
+#[case::unterminated_comment("print 1;\n/* never\nclosed\n".to_string())]
+#[case::unterminated_template("print \"a ${ 1\n+ 2;\nprint 3;\n".to_string())]
+#[case::errors("print @;\nprint \"\\q\";\nprint 0x;\n".to_string())]
+fn chunked_scan_matches_in_memory_scan(
+    #[case] source: String,
+    #[values(1, 7, 4096, 1 << 20)] size: usize,
+    #[values(false, true)] lossless: bool,
+) {
+    assert_eq!(
+        chunked(&source, size, lossless),
+        in_memory(&source, lossless)
+    );
+}
+
+#[test]
+fn a_failed_read_ends_the_scan_and_is_kept() {
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("gone"))
+        }
+    }
+    let mut scanner = ReadScanner::new(Failing);
+    assert!(scanner.next().is_none());
+    assert_eq!(scanner.take_error().unwrap().to_string(), "gone");
+}
@@ -0,0 +1,73 @@
+use rlox::ast::Ast;
+use rlox::interpreter::{Interpreter, Program};
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+use std::rc::Rc;
+
+fn parse(source: &str) -> Ast<'_> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    Parser::new(tokens).parse_program().unwrap()
+}
+
+// the code of each error resolving `source` gives, with the source text it points at
+fn errors(source: &str) -> Vec<(&'static str, &str)> {
+    let ast = parse(source);
+    let errors = Resolver::new(&ast).resolve().unwrap_err();
+    errors
+        .iter()
+        .map(|e| {
+            let diagnostic = e.to_diagnostic();
+            let span = diagnostic.primary.unwrap().span;
+            (diagnostic.code.unwrap(), &source[span.start..span.end])
+        })
+        .collect()
+}
+
+#[rstest]
+#[case::own_initializer("{ var a = 1; { var a = a; } }", "E301", "a")]
+#[case::already_defined("fun f() { var a; var a; }", "E302", "a")]
+#[case::duplicate_parameter("fun f(a, a) {}", "E302", "a")]
+#[case::top_level_return("return 1;", "E303", "return")]
+#[case::value_from_initializer("class A { init() { return 1; } }", "E304", "return")]
+#[case::this_outside_class("print this;", "E305", "this")]
+#[case::this_in_function("fun f() { return this; }", "E305", "this")]
+#[case::inherit_from_self("class A < A {}", "E306", "A")]
+#[case::super_outside_class("fun f() { super.m(); }", "E307", "super")]
+#[case::super_without_superclass("class A { m() { super.m(); } }", "E308", "super")]
+fn misuses_are_resolver_errors(#[case] source: &str, #[case] code: &str, #[case] at: &str) {
+    assert_eq!(errors(source), vec![(code, at)]);
+}
+
+#[rstest]
+#[case::global_redeclared("var a = 1; var a = a;")]
+#[case::shadowed_in_a_block("var a = 1; { var a = 2; }")]
+#[case::bare_return_from_initializer("class A { init() { return; } }")]
+#[case::this_in_a_nested_function("class A { m() { fun f() { return this; } } }")]
+#[case::super_in_a_subclass("class A { m() {} } class B < A { m() { super.m(); } }")]
+fn valid_programs_resolve(#[case] source: &str) {
+    let ast = parse(source);
+    assert!(Resolver::new(&ast).resolve().is_ok());
+}
+
+#[test]
+fn every_error_is_reported() {
+    assert_eq!(
+        errors("return;\nprint this;\nclass A < A {}"),
+        vec![("E303", "return"), ("E305", "this"), ("E306", "A")]
+    );
+}
+
+#[test]
+fn a_closure_keeps_the_variable_it_saw_when_declared() {
+    let source = r#"{ var a = "g"; { fun f() { print a; } f(); var a = "l"; f(); } }"#;
+    let ast = parse(source);
+    let locals = Resolver::new(&ast).resolve().unwrap();
+    let mut output = Vec::new();
+    Interpreter::new()
+        .with_output(Box::new(&mut output))
+        .interpret(Rc::new(Program::new(ast, locals)))
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "g\ng\n");
+}
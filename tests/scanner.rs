@@ -0,0 +1,335 @@
+use rlox::parser::Parser;
+use rlox::scanner::{confusable_identifiers, ScanError, ScanWarning, Scanner};
+use rlox::span::Span;
+use rlox::token_type::TokenType;
+use rlox::trivia::{Trivia, TriviaKind};
+use rstest::rstest;
+use std::borrow::Cow;
+
+fn first_token(source: &str) -> TokenType<'_> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    tokens.into_iter().next().unwrap().typ
+}
+
+#[rstest]
+#[case::unexpected_character(
+    "print @;",
+    ScanError::UnexpectedCharacter("@".into(), Span::new(6, 7, 1, 7))
+)]
+#[case::unexpected_grapheme(
+    "a 🐟 b",
+    ScanError::UnexpectedCharacter("🐟".into(), Span::new(2, 6, 1, 3))
+)]
+#[case::unterminated_string("print \"abc", ScanError::UnterminatedString(Span::new(6, 7, 1, 7)))]
+#[case::invalid_escape("\"\\q\"", ScanError::InvalidEscape("q".into(), Span::new(1, 3, 1, 2)))]
+#[case::invalid_unicode_escape(
+    "\"\\u{110000}\"",
+    ScanError::InvalidUnicodeEscape(Span::new(1, 11, 1, 2))
+)]
+#[case::surrogate_escape(
+    "\"\\u{D800}\"",
+    ScanError::InvalidUnicodeEscape(Span::new(1, 9, 1, 2))
+)]
+#[case::unterminated_template(
+    "print \"a ${1",
+    ScanError::UnterminatedString(Span::new(6, 7, 1, 7))
+)]
+#[case::unterminated_nested_comment(
+    "1;\n/* a /* b */",
+    ScanError::UnterminatedComment(Span::new(3, 5, 2, 1))
+)]
+#[case::unterminated_comment("1;\n/* a", ScanError::UnterminatedComment(Span::new(3, 5, 2, 1)))]
+fn lexical_errors_are_reported_with_their_place(#[case] source: &str, #[case] error: ScanError) {
+    assert_eq!(Scanner::new(source).scan_tokens(), Err(vec![error]));
+}
+
+#[test]
+fn scanning_carries_on_past_errors() {
+    let (tokens, errors) = Scanner::new("@ print 1; #").scan_partial();
+    assert_eq!(
+        errors,
+        vec![
+            ScanError::UnexpectedCharacter("@".into(), Span::new(0, 1, 1, 1)),
+            ScanError::UnexpectedCharacter("#".into(), Span::new(11, 12, 1, 12)),
+        ]
+    );
+    let types = tokens.into_iter().map(|t| t.typ).collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::PRINT,
+            TokenType::NUMBER(1.0),
+            TokenType::SEMICOLON,
+            TokenType::EOF
+        ]
+    );
+}
+
+#[rstest]
+#[case::missing_exponent("print 1e;")]
+#[case::bad_binary_digit("print 0b12;")]
+#[case::missing_hex_digits("print 0x + 1;")]
+fn a_malformed_number_is_not_also_a_parse_error(#[case] source: &str) {
+    let (tokens, errors) = Scanner::new(source).scan_partial();
+    assert!(matches!(errors[..], [ScanError::InvalidNumber(..)]));
+    assert!(Parser::new(tokens).parse_program().is_ok());
+}
+
+#[rstest]
+#[case::newline(r#""a\nb""#, "a\nb")]
+#[case::tab(r#""a\tb""#, "a\tb")]
+#[case::carriage_return(r#""a\rb""#, "a\rb")]
+#[case::backslash(r#""a\\b""#, "a\\b")]
+#[case::quote(r#""say \"hi\"""#, "say \"hi\"")]
+#[case::nul(r#""\0""#, "\0")]
+#[case::dollar(r#""\${a}""#, "${a}")]
+#[case::unicode(r#""caf\u{e9} \u{1F41F}""#, "café 🐟")]
+fn escapes_are_decoded(#[case] source: &str, #[case] value: &str) {
+    assert_eq!(first_token(source), TokenType::STRING(value.into()));
+}
+
+#[test]
+fn a_string_without_escapes_borrows_the_source() {
+    assert!(matches!(
+        first_token("\"plain\""),
+        TokenType::STRING(Cow::Borrowed("plain"))
+    ));
+}
+
+#[rstest]
+#[case::integer("42", 42.0)]
+#[case::fraction("123.45", 123.45)]
+#[case::leading_dot(".5", 0.5)]
+#[case::hex("0x1F", 31.0)]
+#[case::hex_upper("0XfF", 255.0)]
+#[case::octal("0o17", 15.0)]
+#[case::binary("0b1010", 10.0)]
+#[case::exponent("1e3", 1000.0)]
+#[case::signed_exponent("1.5e-3", 0.0015)]
+#[case::upper_exponent("2E+2", 200.0)]
+#[case::separators("1_000_000", 1_000_000.0)]
+#[case::separated_fraction("1_234.567_8", 1_234.567_8)]
+#[case::separated_hex("0xff_ff", 65535.0)]
+fn number_literals_have_their_value(#[case] source: &str, #[case] value: f64) {
+    assert_eq!(first_token(source), TokenType::NUMBER(value));
+}
+
+#[rstest]
+#[case::empty_hex("0x", "expected hexadecimal digits after '0x'")]
+#[case::empty_binary("0b", "expected binary digits after '0b'")]
+#[case::octal_out_of_range("0o8", "expected octal digits after '0o'")]
+#[case::empty_exponent("1e", "expected digits in the exponent")]
+#[case::signed_empty_exponent("1e+", "expected digits in the exponent")]
+#[case::doubled_separator("1__0", "'_' can only separate digits")]
+#[case::trailing_separator("1_", "'_' can only separate digits")]
+#[case::separator_after_prefix("0x_1", "'_' can only separate digits")]
+#[case::binary_out_of_range("0b12", "unexpected characters after the number")]
+#[case::letters_after("12abc", "unexpected characters after the number")]
+fn malformed_number_literals_are_errors(#[case] literal: &str, #[case] reason: &'static str) {
+    let (_, errors) = Scanner::new(literal).scan_partial();
+    let span = Span::new(0, literal.len(), 1, 1);
+    assert_eq!(
+        errors,
+        vec![ScanError::InvalidNumber(literal.into(), reason, span)]
+    );
+}
+
+#[test]
+fn a_dot_after_an_operand_is_a_property_access() {
+    let types = Scanner::new("a.b")
+        .scan_tokens()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.typ)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::IDENTIFIER,
+            TokenType::DOT,
+            TokenType::IDENTIFIER,
+            TokenType::EOF
+        ]
+    );
+}
+
+#[test]
+fn templates_are_split_into_segments() {
+    let types = Scanner::new(r#""a ${b + "c ${1}"} d""#)
+        .scan_tokens()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.typ)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::INTERPOLATION("a ".into()),
+            TokenType::IDENTIFIER,
+            TokenType::PLUS,
+            TokenType::INTERPOLATION("c ".into()),
+            TokenType::NUMBER(1.0),
+            TokenType::STRING("".into()),
+            TokenType::STRING(" d".into()),
+            TokenType::EOF
+        ]
+    );
+}
+
+#[rstest]
+#[case::nested("/* a /* b */ c */ print 1;")]
+#[case::commented_out_code("/*\nprint 0; /* old */\n*/\nprint 1;")]
+#[case::line_comment_inside("/* // */ print 1;")]
+#[case::doc_comments("/// doc\n/** block doc */ print 1;")]
+fn comments_are_skipped_whole(#[case] source: &str) {
+    let types = Scanner::new(source)
+        .scan_tokens()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.typ)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::PRINT,
+            TokenType::NUMBER(1.0),
+            TokenType::SEMICOLON,
+            TokenType::EOF
+        ]
+    );
+}
+
+#[rstest]
+#[case::empty("")]
+#[case::no_trailing_newline("print 1;")]
+#[case::comments(
+    "// leading\nvar a = 1; // trailing\n\n/* block\n spanning */ print a;\n/// doc\nfun f() {}\n"
+)]
+#[case::crlf("var a = 1;\r\n\r\n  print a;\r\n")]
+#[case::tabs_and_unicode("\tvar café = \"naïve ${1 +\n 2}\";\t\n")]
+#[case::errors("print @ 1;\n\"\\q\";\n0x;\n/* never closed\n")]
+#[case::unterminated_string("print \"abc\n")]
+fn lossless_scan_gives_back_the_source(#[case] source: &str) {
+    let (tokens, _) = Scanner::new(source).with_trivia().scan_partial();
+    let text = tokens.iter().map(|t| t.source_text()).collect::<String>();
+    assert_eq!(text, source);
+}
+
+#[test]
+fn trivia_up_to_the_line_end_trails_a_token() {
+    let tokens = Scanner::new("a; // one\n  // two\nb;")
+        .with_trivia()
+        .scan_tokens()
+        .unwrap();
+    let kinds = |trivia: &[Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+    let semicolon = &tokens[1];
+    assert_eq!(
+        kinds(semicolon.trailing()),
+        vec![
+            TriviaKind::Whitespace,
+            TriviaKind::LineComment,
+            TriviaKind::Newline
+        ]
+    );
+    let b = &tokens[2];
+    assert_eq!(
+        kinds(b.leading()),
+        vec![
+            TriviaKind::Whitespace,
+            TriviaKind::LineComment,
+            TriviaKind::Newline
+        ]
+    );
+    assert_eq!(b.leading()[1].text, "// two");
+}
+
+#[test]
+fn trivia_is_only_kept_when_asked_for() {
+    let tokens = Scanner::new("a; // one\nb;").scan_tokens().unwrap();
+    assert!(tokens
+        .iter()
+        .all(|t| t.leading().is_empty() && t.trailing().is_empty()));
+}
+
+#[test]
+fn precomposed_and_combining_accents_give_the_same_name() {
+    let tokens = Scanner::new("café cafe\u{301}").scan_tokens().unwrap();
+    assert_ne!(tokens[0].lexeme, tokens[1].lexeme);
+    assert_eq!(tokens[0].name(), "café");
+    assert_eq!(tokens[1].name(), "café");
+}
+
+#[rstest]
+#[case::mark_on_the_last_letter("ab\u{20DD};", "a", "b\u{20DD}", Span::new(1, 5, 1, 2))]
+#[case::mark_on_the_only_letter("a\u{20DD} b;", "b", "a\u{20DD}", Span::new(0, 4, 1, 1))]
+fn identifiers_end_on_a_grapheme_boundary(
+    #[case] source: &str,
+    #[case] identifier: &str,
+    #[case] unexpected: &str,
+    #[case] span: Span,
+) {
+    let (tokens, errors) = Scanner::new(source).scan_partial();
+    assert_eq!(tokens[0].typ, TokenType::IDENTIFIER);
+    assert_eq!(tokens[0].lexeme, identifier);
+    assert_eq!(
+        errors,
+        vec![ScanError::UnexpectedCharacter(unexpected.into(), span)]
+    );
+}
+
+#[test]
+fn confusable_identifiers_are_warned_about_once_per_name() {
+    // a Latin `a` and a Cyrillic `а`
+    let source = "var a = 1;\nvar \u{430} = 2;\nprint \u{430};\nprint a + \u{430};";
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let warnings = confusable_identifiers(&tokens);
+    assert_eq!(warnings.len(), 1);
+    let ScanWarning::ConfusableIdentifier(name, other) = &warnings[0];
+    assert_eq!((name.name(), name.span.line), ("\u{430}", 2));
+    assert_eq!((other.name(), other.span.line), ("a", 1));
+    assert_eq!(warnings[0].to_diagnostic().code, Some("W101"));
+}
+
+#[test]
+fn distinct_names_are_not_confusable() {
+    let tokens = Scanner::new("var alpha = 1; var beta = 2; var café = 3; var cafe = 4;")
+        .scan_tokens()
+        .unwrap();
+    let names = tokens
+        .iter()
+        .filter(|t| t.typ == TokenType::IDENTIFIER)
+        .map(|t| t.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["alpha", "beta", "café", "cafe"]);
+    assert!(confusable_identifiers(&tokens).is_empty());
+}
+
+#[test]
+fn scanning_one_at_a_time_keeps_errors_in_source_order() {
+    let items = Scanner::new("a @ b;\n\"c")
+        .map(|item| match item {
+            Ok(token) => token.lexeme.to_string(),
+            Err(e) => e.to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        vec![
+            "a".to_string(),
+            ScanError::UnexpectedCharacter("@".into(), Span::new(2, 3, 1, 3)).to_string(),
+            "b".to_string(),
+            ";".to_string(),
+            ScanError::UnterminatedString(Span::new(7, 8, 2, 1)).to_string(),
+            "".to_string(),
+        ]
+    );
+}
+
+#[rstest]
+#[case::accented("naïve = 1;", 7)]
+#[case::combining("nai\u{308}ve = 1;", 7)]
+#[case::emoji("\"🐟🐟\" = 1;", 6)]
+fn columns_count_grapheme_clusters(#[case] source: &str, #[case] column: usize) {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    assert_eq!(tokens[1].span.column, column);
+}
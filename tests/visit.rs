@@ -0,0 +1,55 @@
+use rlox::ast::{Ast, ExprId, StmtId};
+use rlox::expr::Expr;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::visit::{walk_expr, StmtVisitor, Walker};
+
+fn parse(source: &str) -> Ast<'_> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    Parser::new(tokens).parse_program().unwrap()
+}
+
+// the names called directly, wherever the calls are
+#[derive(Default)]
+struct Callees(Vec<String>);
+
+impl<'t, 'a: 't> Walker<'t, 'a> for Callees {
+    fn visit_call(&mut self, ast: &'t Ast<'a>, id: ExprId) {
+        if let Expr::Call { callee, .. } = &ast[id] {
+            if let Expr::Variable { name } = &ast[*callee] {
+                self.0.push(name.name().to_string());
+            }
+        }
+        walk_expr(self, ast, id)
+    }
+}
+
+#[test]
+fn a_walker_only_handles_the_nodes_it_cares_about() {
+    let ast = parse(
+        "fun f(x) { return g(x) + 1; }\nclass A { m() { print h(f(1)); } }\nwhile (k()) { var a = \"${l()}\"; }",
+    );
+    let mut callees = Callees::default();
+    callees.visit_program(&ast);
+    assert_eq!(callees.0, vec!["g", "h", "f", "k", "l"]);
+}
+
+// counts print statements, without looking inside functions
+#[derive(Default)]
+struct TopLevelPrints(usize);
+
+impl<'t, 'a: 't> Walker<'t, 'a> for TopLevelPrints {
+    fn visit_print_stmt(&mut self, _: &'t Ast<'a>, _: StmtId) {
+        self.0 += 1;
+    }
+
+    fn visit_function_stmt(&mut self, _: &'t Ast<'a>, _: StmtId) {}
+}
+
+#[test]
+fn a_walker_can_stop_walking() {
+    let ast = parse("print 1;\nfun f() { print 2; }\n{ if (true) print 3; else print 4; }");
+    let mut prints = TopLevelPrints::default();
+    prints.visit_program(&ast);
+    assert_eq!(prints.0, 3);
+}